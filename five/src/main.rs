@@ -1,14 +1,27 @@
+use std::env;
 use std::io::{self, BufRead};
-use std::num;
 use std::ops::Range;
 use std::str::FromStr;
+use std::{fmt, num};
 
 #[derive(Debug)]
 enum Error {
     ParseInt(num::ParseIntError),
     Io(io::Error),
-    ExtraInput(String),
     Parse(String),
+    Geometry(String),
+    Line(usize, Box<Error>),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ParseInt(e) => write!(f, "{}", e),
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Parse(e) | Self::Geometry(e) => write!(f, "{}", e),
+            Self::Line(line, e) => write!(f, "line {}: {}", line, e),
+        }
+    }
 }
 
 impl From<io::Error> for Error {
@@ -46,7 +59,7 @@ impl FromStr for RowDivider {
         match input {
             "F" => Ok(Self::Front),
             "B" => Ok(Self::Back),
-            c => Err(Error::Parse(format!("expected L/R: {}", c))),
+            c => Err(Error::Parse(format!("expected F/B: {}", c))),
         }
     }
 }
@@ -107,12 +120,95 @@ impl BinarySearcher {
         }
     }
 
-    fn answer(self) -> u32 {
-        assert_eq!(self.range.end, self.range.start + 1);
-        self.range.start
+    fn answer(self) -> Option<u32> {
+        if self.range.end == self.range.start + 1 {
+            Some(self.range.start)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Aircraft {
+    rows: u32,
+    columns: u32,
+}
+
+impl Default for Aircraft {
+    fn default() -> Self {
+        Self {
+            rows: 128,
+            columns: 8,
+        }
     }
 }
 
+impl Aircraft {
+    const MAX_SEATS: u32 = 1 << 20;
+
+    fn new(rows: u32, columns: u32) -> Result<Self> {
+        if !rows.is_power_of_two() || !columns.is_power_of_two() {
+            return Err(Error::Geometry(format!(
+                "dimensions must be powers of two, got {} rows by {} columns",
+                rows, columns
+            )));
+        }
+        match rows.checked_mul(columns) {
+            Some(seats) if seats <= Self::MAX_SEATS => Ok(Self { rows, columns }),
+            _ => Err(Error::Geometry(format!(
+                "{} rows by {} columns is more than {} seats",
+                rows,
+                columns,
+                Self::MAX_SEATS
+            ))),
+        }
+    }
+
+    fn row_bits(&self) -> u32 {
+        self.rows.trailing_zeros()
+    }
+
+    fn column_bits(&self) -> u32 {
+        self.columns.trailing_zeros()
+    }
+
+    fn num_seats(&self) -> u32 {
+        self.rows * self.columns
+    }
+
+    fn seat_id(&self, row: u32, column: u32) -> u32 {
+        row * self.columns + column
+    }
+
+    fn seat(&self, seat_id: u32) -> Result<(u32, u32)> {
+        if seat_id >= self.num_seats() {
+            return Err(Error::Geometry(format!(
+                "seat ID {} is past the last seat {}",
+                seat_id,
+                self.num_seats() - 1
+            )));
+        }
+        Ok((seat_id / self.columns, seat_id % self.columns))
+    }
+}
+
+fn encode_bits<T>(value: u32, bits: u32, lower: T, upper: T) -> Vec<T>
+where
+    T: Copy,
+{
+    (0..bits)
+        .rev()
+        .map(|bit| {
+            if value & (1 << bit) == 0 {
+                lower
+            } else {
+                upper
+            }
+        })
+        .collect()
+}
+
 #[derive(Debug)]
 struct BoardingPass {
     row: Vec<RowDivider>,
@@ -120,46 +216,88 @@ struct BoardingPass {
 }
 
 impl BoardingPass {
-    const NUM_ROWS: u32 = 128;
-    const NUM_COLUMNS: u32 = 8;
+    fn encode(aircraft: &Aircraft, row: u32, column: u32) -> Result<Self> {
+        if row >= aircraft.rows || column >= aircraft.columns {
+            return Err(Error::Geometry(format!(
+                "seat at row {} column {} is outside a {} by {} aircraft",
+                row, column, aircraft.rows, aircraft.columns
+            )));
+        }
+        let pass = Self {
+            row: encode_bits(
+                row,
+                aircraft.row_bits(),
+                RowDivider::Front,
+                RowDivider::Back,
+            ),
+            column: encode_bits(
+                column,
+                aircraft.column_bits(),
+                ColumnDivider::Left,
+                ColumnDivider::Right,
+            ),
+        };
+        debug_assert_eq!(pass.row_number(aircraft).ok(), Some(row));
+        debug_assert_eq!(pass.column_number(aircraft).ok(), Some(column));
+        Ok(pass)
+    }
+
+    fn encode_seat_id(aircraft: &Aircraft, seat_id: u32) -> Result<Self> {
+        let (row, column) = aircraft.seat(seat_id)?;
+        Self::encode(aircraft, row, column)
+    }
 
-    fn seat_id(&self) -> u32 {
-        self.row_number() * 8 + self.column_number()
+    fn seat_id(&self, aircraft: &Aircraft) -> Result<u32> {
+        Ok(aircraft.seat_id(self.row_number(aircraft)?, self.column_number(aircraft)?))
     }
 
-    fn binary_search<T: Clone + Into<BinaryDivider>>(input: &[T], size: u32) -> u32 {
+    fn binary_search<T: Clone + Into<BinaryDivider>>(
+        input: &[T],
+        size: u32,
+        section: &str,
+    ) -> Result<u32> {
+        let wrong_length = || {
+            Error::Parse(format!(
+                "{} section has {} characters, expected {}",
+                section,
+                input.len(),
+                size.trailing_zeros()
+            ))
+        };
+        if input.len() != size.trailing_zeros() as usize {
+            return Err(wrong_length());
+        }
+
         let mut searcher = BinarySearcher::new(size);
         for divider in input {
             searcher.divide(divider.clone().into());
         }
-        searcher.answer()
+        searcher.answer().ok_or_else(wrong_length)
     }
 
-    fn row_number(&self) -> u32 {
-        Self::binary_search(&self.row, Self::NUM_ROWS)
+    fn row_number(&self, aircraft: &Aircraft) -> Result<u32> {
+        Self::binary_search(&self.row, aircraft.rows, "row")
     }
 
-    fn column_number(&self) -> u32 {
-        Self::binary_search(&self.column, Self::NUM_COLUMNS)
+    fn column_number(&self, aircraft: &Aircraft) -> Result<u32> {
+        Self::binary_search(&self.column, aircraft.columns, "column")
     }
 }
 
-fn parse_chars<T: FromStr>(iter: impl Iterator<Item = char>) -> Vec<T> {
-    let mut v = vec![];
-    for c in iter {
-        if let Ok(t) = c.to_string().parse() {
-            v.push(t);
-        } else {
-            break;
+impl fmt::Display for BoardingPass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for r in &self.row {
+            match r {
+                RowDivider::Front => write!(f, "F")?,
+                RowDivider::Back => write!(f, "B")?,
+            }
+        }
+        for c in &self.column {
+            match c {
+                ColumnDivider::Left => write!(f, "L")?,
+                ColumnDivider::Right => write!(f, "R")?,
+            }
         }
-    }
-    v
-}
-
-fn require_no_remaining(remaining: &str) -> Result<()> {
-    if !remaining.is_empty() {
-        Err(Error::ExtraInput(remaining.to_owned()))
-    } else {
         Ok(())
     }
 }
@@ -167,14 +305,30 @@ fn require_no_remaining(remaining: &str) -> Result<()> {
 impl FromStr for BoardingPass {
     type Err = Error;
     fn from_str(input: &str) -> Result<Self> {
-        let row = parse_chars(input.chars());
-        let column = parse_chars(input.chars().skip(row.len()));
-        let remaining = input
-            .chars()
-            .skip(row.len() + column.len())
-            .collect::<String>();
-        require_no_remaining(&remaining)?;
-
+        let mut row = vec![];
+        let mut column = vec![];
+        for (i, c) in input.chars().enumerate() {
+            match c {
+                'F' | 'B' if column.is_empty() => row.push(c.to_string().parse()?),
+                'F' | 'B' => {
+                    return Err(Error::Parse(format!(
+                        "row character {:?} at position {} is in the column section of {:?}",
+                        c,
+                        i + 1,
+                        input
+                    )))
+                }
+                'L' | 'R' => column.push(c.to_string().parse()?),
+                c => {
+                    return Err(Error::Parse(format!(
+                        "unexpected character {:?} at position {} of {:?}",
+                        c,
+                        i + 1,
+                        input
+                    )))
+                }
+            }
+        }
         Ok(Self { row, column })
     }
 }
@@ -184,44 +338,224 @@ where
     Error: From<<T as FromStr>::Err>,
 {
     let mut values = vec![];
-    for maybe_line in lines.lines() {
-        values.push(maybe_line?.parse()?);
+    for (i, maybe_line) in lines.lines().enumerate() {
+        let value = maybe_line?
+            .parse::<T>()
+            .map_err(|e| Error::Line(i + 1, Box::new(e.into())))?;
+        values.push(value);
     }
     Ok(values)
 }
 
-fn part_one(passes: &[BoardingPass]) {
-    let max_seat_id = passes.iter().map(|p| p.seat_id()).max();
-    println!("{}", max_seat_id.unwrap());
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SeatState {
+    Occupied,
+    Duplicate,
+    Free,
+    Nonexistent,
 }
 
-fn part_two(passes: &[BoardingPass]) {
-    let mut seat_ids: Vec<_> = passes.iter().map(|p| p.seat_id()).collect();
-    seat_ids.sort();
+impl fmt::Display for SeatState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Occupied => write!(f, "#"),
+            Self::Duplicate => write!(f, "!"),
+            Self::Free => write!(f, "."),
+            Self::Nonexistent => write!(f, "-"),
+        }
+    }
+}
+
+struct SeatMap {
+    aircraft: Aircraft,
+    occupants: Vec<Vec<usize>>,
+    out_of_range: Vec<(usize, Error)>,
+    occupied: Option<(u32, u32)>,
+}
+
+impl SeatMap {
+    fn new(aircraft: Aircraft, passes: &[BoardingPass]) -> Self {
+        let mut occupants = vec![vec![]; aircraft.num_seats() as usize];
+        let mut out_of_range = vec![];
+        for (i, pass) in passes.iter().enumerate() {
+            match pass.seat_id(&aircraft) {
+                Ok(seat_id) => occupants[seat_id as usize].push(i),
+                Err(e) => out_of_range.push((i, e)),
+            }
+        }
+        let mut occupied_ids =
+            (0..aircraft.num_seats()).filter(|&id| !occupants[id as usize].is_empty());
+        let occupied = occupied_ids
+            .next()
+            .map(|first| (first, occupied_ids.next_back().unwrap_or(first)));
+        Self {
+            aircraft,
+            occupants,
+            out_of_range,
+            occupied,
+        }
+    }
+
+    fn last_occupied(&self) -> Option<u32> {
+        self.occupied.map(|(_, last)| last)
+    }
+
+    fn state(&self, seat_id: u32) -> SeatState {
+        let (first, last) = match self.occupied {
+            Some(bounds) => bounds,
+            None => return SeatState::Nonexistent,
+        };
+        match self.occupants[seat_id as usize].len() {
+            0 if seat_id < first || seat_id > last => SeatState::Nonexistent,
+            0 => SeatState::Free,
+            1 => SeatState::Occupied,
+            _ => SeatState::Duplicate,
+        }
+    }
 
-    let mut iter = seat_ids.iter().peekable();
+    fn gaps(&self) -> Vec<u32> {
+        (0..self.aircraft.num_seats())
+            .filter(|&id| self.state(id) == SeatState::Free)
+            .collect()
+    }
 
-    let mut holes = vec![];
-    while let Some(value) = iter.next() {
-        if let Some(next_value) = iter.peek() {
-            if value + 1 != **next_value {
-                holes.push(value + 1);
+    fn duplicates(&self) -> Vec<(u32, &[usize])> {
+        self.occupants
+            .iter()
+            .enumerate()
+            .filter(|(_, o)| o.len() > 1)
+            .map(|(id, o)| (id as u32, &o[..]))
+            .collect()
+    }
+}
+
+impl fmt::Display for SeatMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in 0..self.aircraft.rows {
+            write!(f, "{:4} ", row)?;
+            for column in 0..self.aircraft.columns {
+                write!(f, "{}", self.state(self.aircraft.seat_id(row, column)))?;
             }
+            writeln!(f)?;
         }
+        Ok(())
     }
+}
+
+fn print_seat_report(map: &SeatMap, passes: &[BoardingPass]) {
+    print!("{}", map);
+
+    let gaps: Vec<_> = map.gaps().iter().map(|id| id.to_string()).collect();
+    println!("free seats: {}", gaps.join(", "));
 
-    assert_eq!(holes.len(), 1);
-    println!("{}", holes[0]);
+    for (seat_id, indices) in map.duplicates() {
+        let lines: Vec<_> = indices.iter().map(|i| (i + 1).to_string()).collect();
+        println!(
+            "seat {} assigned to {} passes on lines {}",
+            seat_id,
+            indices.len(),
+            lines.join(", ")
+        );
+    }
+
+    for (i, e) in &map.out_of_range {
+        println!(
+            "line {}: {} does not fit a {} by {} aircraft: {}",
+            i + 1,
+            passes[*i],
+            map.aircraft.rows,
+            map.aircraft.columns,
+            e
+        );
+    }
+}
+
+fn part_one(map: &SeatMap) {
+    match map.last_occupied() {
+        Some(seat_id) => println!("{}", seat_id),
+        None => println!("no boarding passes"),
+    }
+}
+
+fn part_two(map: &SeatMap) {
+    match &map.gaps()[..] {
+        [seat_id] => println!("{}", seat_id),
+        [] => println!("no free seat"),
+        gaps => println!("{} free seats: {:?}", gaps.len(), gaps),
+    }
+}
+
+fn parse_seat(input: &str) -> Result<(u32, u32)> {
+    let mut parts = input.splitn(2, ',');
+    let row = parts.next().unwrap().parse()?;
+    let column = parts
+        .next()
+        .ok_or_else(|| Error::Parse(format!("expected ROW,COLUMN: {}", input)))?
+        .parse()?;
+    Ok((row, column))
+}
+
+enum Encode {
+    Seat(u32, u32),
+    SeatId(u32),
+}
+
+impl Encode {
+    fn encode(&self, aircraft: &Aircraft) -> Result<BoardingPass> {
+        match *self {
+            Self::Seat(row, column) => BoardingPass::encode(aircraft, row, column),
+            Self::SeatId(seat_id) => BoardingPass::encode_seat_id(aircraft, seat_id),
+        }
+    }
 }
 
 fn main() -> Result<()> {
+    let Aircraft {
+        mut rows,
+        mut columns,
+    } = Aircraft::default();
+    let mut to_encode = vec![];
+    let mut show_map = false;
+    for arg in env::args().skip(1) {
+        match arg.splitn(2, '=').collect::<Vec<_>>()[..] {
+            ["--rows", value] => rows = value.parse()?,
+            ["--columns", value] => columns = value.parse()?,
+            ["--encode", seat] => {
+                let (row, column) = parse_seat(seat)?;
+                to_encode.push(Encode::Seat(row, column));
+            }
+            ["--encode-id", seat_id] => to_encode.push(Encode::SeatId(seat_id.parse()?)),
+            ["--map"] => show_map = true,
+            _ => return Err(Error::Parse(format!("unknown argument {}", arg))),
+        }
+    }
+    let aircraft = Aircraft::new(rows, columns)?;
+
+    if !to_encode.is_empty() {
+        for e in &to_encode {
+            println!("{}", e.encode(&aircraft)?);
+        }
+        return Ok(());
+    }
+
     let passes: Vec<BoardingPass> = parse_lines(io::stdin().lock())?;
+    let mut map = SeatMap::new(aircraft, &passes);
+
+    if show_map {
+        print_seat_report(&map, &passes);
+        return Ok(());
+    }
+
+    if !map.out_of_range.is_empty() {
+        let (i, e) = map.out_of_range.remove(0);
+        return Err(Error::Line(i + 1, Box::new(e)));
+    }
 
     println!("Part 1");
-    part_one(&passes);
+    part_one(&map);
 
     println!("Part 2");
-    part_two(&passes);
+    part_two(&map);
 
     Ok(())
 }
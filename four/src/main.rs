@@ -1,11 +1,16 @@
-use combine::parser::char::{alpha_num, char, digit, letter, string};
+use combine::parser::char::{alpha_num, char, digit, letter};
 use combine::parser::repeat::count_min_max;
+use combine::parser::token::position;
+use combine::stream::position::{self as stream_position, SourcePosition};
 use combine::stream::Stream;
-use combine::{many1, sep_by, sep_end_by, EasyParser, Parser};
+use combine::{many1, sep_end_by, EasyParser, Parser};
 use std::collections::HashMap;
-use std::io::{self, Read};
-use std::matches;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead};
 use std::num;
+use std::ops::RangeInclusive;
 use std::str::FromStr;
 
 fn require_no_remaining(remaining: &str) -> Result<()> {
@@ -35,6 +40,20 @@ enum Error {
     Io(io::Error),
     ParseError(String),
     ExtraneousInput(String),
+    Schema(String),
+    InvalidKeys(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ParseInt(e) => write!(f, "{}", e),
+            Self::Io(e) => write!(f, "{}", e),
+            Self::ParseError(e) => write!(f, "{}", e.trim_end().replace('\n', "; ")),
+            Self::ExtraneousInput(e) => write!(f, "unexpected trailing input {:?}", e),
+            Self::Schema(e) | Self::InvalidKeys(e) => write!(f, "{}", e),
+        }
+    }
 }
 
 impl From<io::Error> for Error {
@@ -68,9 +87,9 @@ impl FromStr for Year {
 }
 
 #[derive(Debug)]
-enum Length {
-    Cm(usize),
-    In(usize),
+struct Length {
+    value: usize,
+    unit: String,
 }
 
 impl Length {
@@ -79,12 +98,8 @@ impl Length {
         Input: Stream<Token = char>,
     {
         let number = many1(digit()).map(|v: String| v.parse().unwrap());
-        let parser = number.and(string("cm").or(string("in")));
-        parser.map(|(value, units)| match units {
-            "cm" => Self::Cm(value),
-            "in" => Self::In(value),
-            _ => unreachable!(),
-        })
+        let parser = number.and(many1(letter()));
+        parser.map(|(value, unit)| Self { value, unit })
     }
 }
 
@@ -114,234 +129,606 @@ impl HexColor {
 parser_from_str!(HexColor);
 
 #[derive(Debug)]
-struct SimpleColor(String);
-
-impl FromStr for SimpleColor {
-    type Err = Error;
-    fn from_str(input: &str) -> Result<Self> {
-        match input {
-            "amb" => Ok(Self(input.to_owned())),
-            "blu" => Ok(Self(input.to_owned())),
-            "brn" => Ok(Self(input.to_owned())),
-            "gry" => Ok(Self(input.to_owned())),
-            "grn" => Ok(Self(input.to_owned())),
-            "hzl" => Ok(Self(input.to_owned())),
-            "oth" => Ok(Self(input.to_owned())),
-            c => Err(Error::ParseError(format!("invalid simple color {}", c))),
-        }
-    }
-}
+struct Choice(String);
 
 #[derive(Debug)]
-struct PassportId(usize);
+struct Digits(String);
 
-impl PassportId {
-    fn parser<Input>() -> impl Parser<Input, Output = Self>
-    where
-        Input: Stream<Token = char>,
-    {
-        count_min_max(9, 9, digit()).map(|v: String| Self(v.parse().unwrap()))
+impl Digits {
+    fn parse(input: &str, width: usize) -> Result<Self> {
+        let (p, remaining): (Self, &str) = count_min_max(width, width, digit())
+            .map(Self)
+            .easy_parse(input)?;
+        require_no_remaining(remaining)?;
+        Ok(p)
     }
 }
 
-parser_from_str!(PassportId);
-
 #[derive(Debug)]
 enum Value {
     Year(Year),
     HexColor(HexColor),
-    SimpleColor(SimpleColor),
+    Choice(Choice),
     Length(Length),
-    PassportId(PassportId),
+    Digits(Digits),
     Other(String),
 }
 
-impl Value {
-    fn valid_birth_year(&self) -> bool {
-        if let Self::Year(Year(y)) = self {
-            *y >= 1920 && *y <= 2002
-        } else {
-            false
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Year(Year(y)) => write!(f, "{}", y),
+            Self::HexColor(HexColor(c)) => write!(f, "#{}", c),
+            Self::Choice(Choice(c)) => write!(f, "{}", c),
+            Self::Length(Length { value, unit }) => write!(f, "{}{}", value, unit),
+            Self::Digits(Digits(d)) => write!(f, "{}", d),
+            Self::Other(o) => write!(f, "{}", o),
         }
     }
+}
+
+fn format_range(range: &RangeInclusive<usize>) -> String {
+    format!("{}-{}", range.start(), range.end())
+}
+
+fn parse_range(input: &str) -> Result<RangeInclusive<usize>> {
+    let mut parts = input.splitn(2, '-');
+    let start = parts.next().unwrap().parse()?;
+    let end = parts
+        .next()
+        .ok_or_else(|| Error::Schema(format!("bad range {}", input)))?
+        .parse()?;
+    Ok(start..=end)
+}
+
+#[derive(Debug)]
+enum FieldType {
+    Year(RangeInclusive<usize>),
+    Length(Vec<(String, RangeInclusive<usize>)>),
+    HexColor,
+    Choice(Vec<String>),
+    Digits(usize),
+    Any,
+}
 
-    fn valid_issue_year(&self) -> bool {
-        if let Self::Year(Year(y)) = self {
-            *y >= 2010 && *y <= 2020
-        } else {
-            false
+impl FieldType {
+    fn from_words(name: &str, args: &[&str]) -> Result<Self> {
+        let bad_arguments = || Error::Schema(format!("bad arguments for {}: {:?}", name, args));
+        match (name, args) {
+            ("year", [range]) => Ok(Self::Year(parse_range(range)?)),
+            ("length", units) if !units.is_empty() => Ok(Self::Length(
+                units
+                    .iter()
+                    .map(|u| {
+                        let mut parts = u.splitn(2, ':');
+                        let unit = parts.next().unwrap().to_owned();
+                        let range = parse_range(parts.next().ok_or_else(bad_arguments)?)?;
+                        Ok((unit, range))
+                    })
+                    .collect::<Result<_>>()?,
+            )),
+            ("hex-color", []) => Ok(Self::HexColor),
+            ("enum", options) if !options.is_empty() => Ok(Self::Choice(
+                options.iter().map(|&o| o.to_owned()).collect(),
+            )),
+            ("digits", [width]) => Ok(Self::Digits(width.parse()?)),
+            ("any", []) => Ok(Self::Any),
+            ("year", _)
+            | ("length", _)
+            | ("hex-color", _)
+            | ("enum", _)
+            | ("digits", _)
+            | ("any", _) => Err(bad_arguments()),
+            (t, _) => Err(Error::Schema(format!("unknown field type {}", t))),
         }
     }
 
-    fn valid_expiration_year(&self) -> bool {
-        if let Self::Year(Year(y)) = self {
-            *y >= 2020 && *y <= 2030
-        } else {
-            false
+    fn parse_value(&self, input: &str) -> Option<Value> {
+        match self {
+            Self::Year(_) => input.parse().ok().map(Value::Year),
+            Self::Length(_) => input.parse().ok().map(Value::Length),
+            Self::HexColor => input.parse().ok().map(Value::HexColor),
+            Self::Choice(options) if options.iter().any(|o| o == input) => {
+                Some(Value::Choice(Choice(input.to_owned())))
+            }
+            Self::Choice(_) => None,
+            Self::Digits(width) => Digits::parse(input, *width).ok().map(Value::Digits),
+            Self::Any => None,
         }
     }
 
-    fn valid_height(&self) -> bool {
-        if let Self::Length(Length::Cm(h)) = self {
-            *h >= 150 && *h <= 193
-        } else if let Self::Length(Length::In(h)) = self {
-            *h >= 59 && *h <= 76
-        } else {
-            false
+    fn check(&self, value: &Value) -> std::result::Result<(), String> {
+        match (self, value) {
+            (Self::Year(range), Value::Year(Year(y))) if !range.contains(y) => {
+                Err(format!("out of range {}", format_range(range)))
+            }
+            (Self::Year(_), Value::Year(_)) => Ok(()),
+            (Self::Year(_), _) => Err("is not a year".into()),
+            (Self::Length(units), Value::Length(length)) => {
+                match units.iter().find(|(unit, _)| *unit == length.unit) {
+                    Some((_, range)) if range.contains(&length.value) => Ok(()),
+                    Some((_, range)) => Err(format!("out of range {}", format_range(range))),
+                    None => Err(format!("has unknown unit {}", length.unit)),
+                }
+            }
+            (Self::Length(_), _) => Err("is not a length".into()),
+            (Self::HexColor, Value::HexColor(_)) => Ok(()),
+            (Self::HexColor, _) => Err("is not a hex color".into()),
+            (Self::Choice(_), Value::Choice(_)) => Ok(()),
+            (Self::Choice(options), _) => Err(format!("is not one of {}", options.join(" "))),
+            (Self::Digits(_), Value::Digits(_)) => Ok(()),
+            (Self::Digits(width), _) => Err(format!("is not {} digits", width)),
+            (Self::Any, _) => Ok(()),
         }
     }
+}
 
-    fn valid_hair_color(&self) -> bool {
-        matches!(self, Self::HexColor(_))
+#[derive(Debug)]
+struct FieldRule {
+    key: String,
+    required: bool,
+    field_type: FieldType,
+}
+
+impl FromStr for FieldRule {
+    type Err = Error;
+    fn from_str(input: &str) -> Result<Self> {
+        let words: Vec<&str> = input.split_whitespace().collect();
+        match &words[..] {
+            [key, required, field_type, args @ ..] => {
+                let required = match *required {
+                    "required" => true,
+                    "optional" => false,
+                    r => {
+                        return Err(Error::Schema(format!(
+                            "expected required or optional, got {}",
+                            r
+                        )))
+                    }
+                };
+                Ok(Self {
+                    key: (*key).to_owned(),
+                    required,
+                    field_type: FieldType::from_words(field_type, args)?,
+                })
+            }
+            _ => Err(Error::Schema(format!("bad field rule {}", input))),
+        }
     }
+}
 
-    fn valid_eye_color(&self) -> bool {
-        matches!(self, Self::SimpleColor(_))
+#[derive(Debug)]
+struct Schema(Vec<FieldRule>);
+
+const DEFAULT_SCHEMA: &str = include_str!("../schema.txt");
+
+impl Schema {
+    fn load(path: &str) -> Result<Self> {
+        fs::read_to_string(path)?.parse()
+    }
+
+    fn field(&self, key: &str) -> Option<&FieldRule> {
+        self.0.iter().find(|f| f.key == key)
     }
+}
 
-    fn valid_passport_id(&self) -> bool {
-        matches!(self, Self::PassportId(_))
+impl FromStr for Schema {
+    type Err = Error;
+    fn from_str(input: &str) -> Result<Self> {
+        let rules = input
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(str::parse)
+            .collect::<Result<_>>()?;
+        Ok(Self(rules))
+    }
+}
+
+#[derive(Debug)]
+struct InvalidField {
+    key: String,
+    value: String,
+    reason: String,
+}
+
+impl fmt::Display for InvalidField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} {}", self.key, self.value, self.reason)
+    }
+}
+
+#[derive(Debug, Default)]
+struct ValidationReport {
+    missing: Vec<String>,
+    invalid: Vec<InvalidField>,
+}
+
+impl ValidationReport {
+    fn is_valid(&self) -> bool {
+        self.missing.is_empty() && self.invalid.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_valid() {
+            return writeln!(f, "valid");
+        }
+        writeln!(f, "invalid")?;
+        for key in &self.missing {
+            writeln!(f, "    missing {}", key)?;
+        }
+        for field in &self.invalid {
+            writeln!(f, "    {}", field)?;
+        }
+        Ok(())
     }
 }
 
 #[derive(Debug)]
 struct Passport(HashMap<String, Value>);
 
+#[derive(Debug)]
+enum KeyIssue {
+    Duplicate {
+        key: String,
+        position: SourcePosition,
+        first: SourcePosition,
+    },
+    Unknown {
+        key: String,
+        position: SourcePosition,
+    },
+}
+
+impl fmt::Display for KeyIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Duplicate {
+                key,
+                position,
+                first,
+            } => write!(
+                f,
+                "line {}, column {}: duplicate key {} (first seen at line {}, column {})",
+                position.line, position.column, key, first.line, first.column
+            ),
+            Self::Unknown { key, position } => write!(
+                f,
+                "line {}, column {}: unknown key {}",
+                position.line, position.column, key
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyMode {
+    Strict,
+    Lenient,
+}
+
 impl Passport {
-    fn from_strings(map: HashMap<String, String>) -> Self {
+    fn from_strings(
+        fields: Vec<(SourcePosition, String, String)>,
+        schema: &Schema,
+    ) -> (Self, Vec<KeyIssue>) {
         let mut new_map = HashMap::new();
-        for (k, v) in map {
-            match k.as_str() {
-                "byr" | "iyr" | "eyr" => {
-                    if let Ok(v) = v.parse() {
-                        new_map.insert(k, Value::Year(v));
-                        continue;
-                    }
-                }
-                "hgt" => {
-                    if let Ok(v) = v.parse() {
-                        new_map.insert(k, Value::Length(v));
-                        continue;
-                    }
-                }
-                "hcl" => {
-                    if let Ok(v) = v.parse() {
-                        new_map.insert(k, Value::HexColor(v));
-                        continue;
-                    }
-                }
-                "ecl" => {
-                    if let Ok(v) = v.parse() {
-                        new_map.insert(k, Value::SimpleColor(v));
-                        continue;
-                    }
-                }
-                "pid" => {
-                    if let Ok(v) = v.parse() {
-                        new_map.insert(k, Value::PassportId(v));
-                        continue;
-                    }
-                }
-                _ => (),
+        let mut seen: HashMap<String, SourcePosition> = HashMap::new();
+        let mut issues = vec![];
+        for (position, k, v) in fields {
+            if let Some(&first) = seen.get(&k) {
+                issues.push(KeyIssue::Duplicate {
+                    key: k.clone(),
+                    position,
+                    first,
+                });
+            } else {
+                seen.insert(k.clone(), position);
+            }
+            let field = schema.field(&k);
+            if field.is_none() {
+                issues.push(KeyIssue::Unknown {
+                    key: k.clone(),
+                    position,
+                });
             }
-            new_map.insert(k, Value::Other(v));
+            let value = field
+                .and_then(|f| f.field_type.parse_value(&v))
+                .unwrap_or(Value::Other(v));
+            new_map.insert(k, value);
         }
-        Self(new_map)
+        (Self(new_map), issues)
     }
 
-    fn parser<Input>() -> impl Parser<Input, Output = Self>
+    fn parser<'a, Input>(
+        schema: &'a Schema,
+    ) -> impl Parser<Input, Output = (Self, Vec<KeyIssue>)> + 'a
     where
-        Input: Stream<Token = char>,
+        Input: Stream<Token = char, Position = SourcePosition> + 'a,
     {
         let value = alpha_num().or(char('#'));
-        let key_value = many1(letter()).skip(char(':')).and(many1(value));
+        let key_value = (position(), many1(letter()).skip(char(':')), many1(value));
         let separator = char(' ').or(char('\n'));
-        sep_end_by(key_value, separator).map(|v: Vec<_>| {
-            Self::from_strings(v.iter().cloned().collect::<HashMap<String, String>>())
-        })
+        sep_end_by(key_value, separator).map(move |v| Self::from_strings(v, schema))
     }
 
-    fn part_one_valid(&self) -> bool {
-        self.0.contains_key("byr")
-            && self.0.contains_key("iyr")
-            && self.0.contains_key("eyr")
-            && self.0.contains_key("hgt")
-            && self.0.contains_key("hcl")
-            && self.0.contains_key("ecl")
-            && self.0.contains_key("pid")
+    fn validate(&self, schema: &Schema) -> ValidationReport {
+        let mut report = ValidationReport::default();
+        for field in &schema.0 {
+            match self.0.get(&field.key) {
+                Some(value) => {
+                    if let Err(reason) = field.field_type.check(value) {
+                        report.invalid.push(InvalidField {
+                            key: field.key.clone(),
+                            value: value.to_string(),
+                            reason,
+                        });
+                    }
+                }
+                None if field.required => report.missing.push(field.key.clone()),
+                None => {}
+            }
+        }
+        report
     }
 
-    fn part_two_valid(&self) -> bool {
-        if !self.part_one_valid() {
-            return false;
+    fn parse(
+        input: &str,
+        schema: &Schema,
+        first_line: usize,
+        mode: KeyMode,
+    ) -> Result<(Self, Vec<KeyIssue>)> {
+        let start = SourcePosition {
+            line: first_line as i32,
+            column: 1,
+        };
+        let ((p, issues), remaining) = Self::parser(schema)
+            .easy_parse(stream_position::Stream::with_positioner(input, start))
+            .map_err(|e| Error::ParseError(e.to_string()))?;
+        require_no_remaining(remaining.input)?;
+        if mode == KeyMode::Strict && !issues.is_empty() {
+            let issues: Vec<_> = issues.iter().map(|i| i.to_string()).collect();
+            return Err(Error::InvalidKeys(issues.join("; ")));
         }
-        for (k, v) in &self.0 {
-            let valid = match k.as_str() {
-                "byr" => v.valid_birth_year(),
-                "iyr" => v.valid_issue_year(),
-                "eyr" => v.valid_expiration_year(),
-                "hgt" => v.valid_height(),
-                "hcl" => v.valid_hair_color(),
-                "ecl" => v.valid_eye_color(),
-                "pid" => v.valid_passport_id(),
-                _ => true,
-            };
-            if !valid {
-                return false;
+        Ok((p, issues))
+    }
+
+    fn sorted_fields(&self) -> Vec<(&String, &Value)> {
+        let mut fields: Vec<_> = self.0.iter().collect();
+        fields.sort_by_key(|&(k, _)| k);
+        fields
+    }
+
+    fn to_json(&self) -> String {
+        let fields: Vec<_> = self
+            .sorted_fields()
+            .into_iter()
+            .map(|(k, v)| format!("{}:{}", json_string(k), json_string(&v.to_string())))
+            .collect();
+        format!("{{{}}}", fields.join(","))
+    }
+
+    fn part_one_valid(&self, schema: &Schema) -> bool {
+        self.validate(schema).missing.is_empty()
+    }
+
+    fn part_two_valid(&self, schema: &Schema) -> bool {
+        self.validate(schema).is_valid()
+    }
+}
+
+impl fmt::Display for Passport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (k, v)) in self.sorted_fields().into_iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
             }
+            write!(f, "{}:{}", k, v)?;
         }
-        true
+        Ok(())
     }
 }
 
-parser_from_str!(Passport);
-
 #[derive(Debug)]
 struct PassportCollection(Vec<Passport>);
 
 impl PassportCollection {
-    fn parser<Input>() -> impl Parser<Input, Output = Self>
-    where
-        Input: Stream<Token = char>,
-    {
-        let passport = Passport::parser();
-        sep_by(passport, char('\n')).map(|v| Self(v))
+    fn to_json(&self) -> String {
+        let passports: Vec<_> = self.0.iter().map(|p| p.to_json()).collect();
+        format!("[{}]", passports.join(","))
     }
 }
 
-impl FromStr for PassportCollection {
-    type Err = Error;
-    fn from_str(input: &str) -> Result<Self> {
-        let (p, remaining): (Self, &str) = Self::parser().easy_parse(input)?;
-        require_no_remaining(remaining)?;
-        Ok(p)
+impl fmt::Display for PassportCollection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, passport) in self.0.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "{}", passport)?;
+        }
+        Ok(())
     }
 }
 
-fn part_one(passports: &PassportCollection) {
+struct Record {
+    index: usize,
+    passport: Result<Passport>,
+    warnings: Vec<KeyIssue>,
+}
+
+struct PassportReader<'a, R> {
+    lines: io::Lines<R>,
+    schema: &'a Schema,
+    mode: KeyMode,
+    record: usize,
+    line: usize,
+}
+
+impl<'a, R: BufRead> PassportReader<'a, R> {
+    fn new(input: R, schema: &'a Schema, mode: KeyMode) -> Self {
+        Self {
+            lines: input.lines(),
+            schema,
+            mode,
+            record: 0,
+            line: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for PassportReader<'_, R> {
+    type Item = Record;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut text = String::new();
+        let mut first_line = 0;
+        for maybe_line in &mut self.lines {
+            self.line += 1;
+            let line = match maybe_line {
+                Ok(line) => line,
+                Err(e) => {
+                    self.record += 1;
+                    return Some(Record {
+                        index: self.record,
+                        passport: Err(e.into()),
+                        warnings: vec![],
+                    });
+                }
+            };
+            if line.trim().is_empty() {
+                if text.is_empty() {
+                    continue;
+                }
+                break;
+            }
+            if text.is_empty() {
+                first_line = self.line;
+            } else {
+                text.push('\n');
+            }
+            text.push_str(&line);
+        }
+
+        if text.is_empty() {
+            return None;
+        }
+        self.record += 1;
+        let parsed = Passport::parse(&text, self.schema, first_line, self.mode);
+        let (passport, warnings) = match parsed {
+            Ok((p, warnings)) => (Ok(p), warnings),
+            Err(e) => (Err(e), vec![]),
+        };
+        Some(Record {
+            index: self.record,
+            passport,
+            warnings,
+        })
+    }
+}
+
+fn json_string(input: &str) -> String {
+    let mut out = String::from("\"");
+    for c in input.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn part_one(passports: &PassportCollection, schema: &Schema) {
     println!(
         "{:?}",
-        passports.0.iter().filter(|p| p.part_one_valid()).count()
+        passports
+            .0
+            .iter()
+            .filter(|p| p.part_one_valid(schema))
+            .count()
     );
 }
 
-fn part_two(passports: &PassportCollection) {
+fn part_two(passports: &PassportCollection, schema: &Schema) {
     println!(
         "{:?}",
-        passports.0.iter().filter(|p| p.part_two_valid()).count()
+        passports
+            .0
+            .iter()
+            .filter(|p| p.part_two_valid(schema))
+            .count()
     );
 }
 
+enum Output {
+    Answers,
+    Report,
+    Canonical,
+    Json,
+}
+
 fn main() -> Result<()> {
-    let mut input = String::new();
-    io::stdin().read_to_string(&mut input)?;
-    let passports: PassportCollection = input.parse()?;
+    let mut schema: Schema = DEFAULT_SCHEMA.parse()?;
+    let mut output = Output::Answers;
+    let mut mode = KeyMode::Lenient;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--strict" => mode = KeyMode::Strict,
+            "--report" => output = Output::Report,
+            "--canonical" => output = Output::Canonical,
+            "--json" => output = Output::Json,
+            a => match a.strip_prefix("--schema=") {
+                Some(path) => schema = Schema::load(path)?,
+                None => return Err(Error::Schema(format!("unknown argument {}", a))),
+            },
+        }
+    }
 
-    println!("Part 1");
-    part_one(&passports);
+    let stdin = io::stdin();
+    let records = PassportReader::new(stdin.lock(), &schema, mode);
 
-    println!("Part 2");
-    part_two(&passports);
+    if let Output::Report = output {
+        for record in records {
+            match record.passport {
+                Ok(p) => print!("record {}: {}", record.index, p.validate(&schema)),
+                Err(e) => println!("record {}: rejected: {}", record.index, e),
+            }
+            for warning in &record.warnings {
+                println!("    warning: {}", warning);
+            }
+        }
+        return Ok(());
+    }
+
+    let mut passports = vec![];
+    for record in records {
+        for warning in &record.warnings {
+            eprintln!("record {}: warning: {}", record.index, warning);
+        }
+        match record.passport {
+            Ok(p) => passports.push(p),
+            Err(e) => eprintln!("record {}: skipping: {}", record.index, e),
+        }
+    }
+    let passports = PassportCollection(passports);
+
+    match output {
+        Output::Answers => {
+            println!("Part 1");
+            part_one(&passports, &schema);
+
+            println!("Part 2");
+            part_two(&passports, &schema);
+        }
+        Output::Report => unreachable!(),
+        Output::Canonical => print!("{}", passports),
+        Output::Json => println!("{}", passports.to_json()),
+    }
 
     Ok(())
 }
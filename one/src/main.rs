@@ -1,11 +1,26 @@
+use std::cmp::Ordering;
+use std::env;
+use std::fmt;
 use std::io::{self, BufRead};
 use std::num;
+use std::process;
 use std::str::FromStr;
 
 #[derive(Debug)]
 enum Error {
     ParseInt(num::ParseIntError),
     Io(io::Error),
+    Usage(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ParseInt(e) => write!(f, "{}", e),
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Usage(e) => write!(f, "{}", e),
+        }
+    }
 }
 
 impl From<io::Error> for Error {
@@ -33,36 +48,245 @@ where
     Ok(values)
 }
 
-fn part_one(numbers: &[u32]) {
-    for (i, number1) in numbers.iter().enumerate() {
-        for number2 in numbers[(i + 1)..].iter() {
-            if number1 + number2 == 2020 {
-                println!("{}", number1 * number2)
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct KSum {
+    values: Vec<u32>,
+    indices: Vec<usize>,
+}
+
+impl KSum {
+    fn from_entries(entries: Vec<(usize, u32)>) -> Self {
+        let (indices, values) = entries.into_iter().unzip();
+        Self { values, indices }
+    }
+
+    fn product(&self) -> u64 {
+        self.values.iter().map(|&v| v as u64).product()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Duplicates {
+    Distinct,
+    Merged,
+}
+
+fn sorted_entries(numbers: &[u32], duplicates: Duplicates) -> Vec<(usize, u32)> {
+    let mut entries: Vec<_> = numbers.iter().copied().enumerate().collect();
+    entries.sort_by_key(|&(_, v)| v);
+    if duplicates == Duplicates::Merged {
+        entries.dedup_by_key(|&mut (_, v)| v);
+    }
+    entries
+}
+
+fn two_sum_sorted(entries: &[(usize, u32)], target: u32) -> Option<Vec<(usize, u32)>> {
+    if entries.is_empty() {
+        return None;
+    }
+
+    let mut low = 0;
+    let mut high = entries.len() - 1;
+    while low < high {
+        let sum = entries[low].1 as u64 + entries[high].1 as u64;
+        match sum.cmp(&(target as u64)) {
+            Ordering::Equal => return Some(vec![entries[low], entries[high]]),
+            Ordering::Less => low += 1,
+            Ordering::Greater => high -= 1,
+        }
+    }
+    None
+}
+
+fn k_sum_sorted(entries: &[(usize, u32)], target: u32, k: usize) -> Option<Vec<(usize, u32)>> {
+    match k {
+        0 if target == 0 => Some(vec![]),
+        0 => None,
+        1 => entries
+            .binary_search_by_key(&target, |&(_, v)| v)
+            .ok()
+            .map(|i| vec![entries[i]]),
+        2 => two_sum_sorted(entries, target),
+        _ => {
+            for (i, &entry) in entries.iter().enumerate() {
+                if entry.1 > target {
+                    break;
+                }
+                if let Some(mut rest) = k_sum_sorted(&entries[(i + 1)..], target - entry.1, k - 1) {
+                    rest.insert(0, entry);
+                    return Some(rest);
+                }
             }
+            None
         }
     }
 }
 
-fn part_two(numbers: &[u32]) {
-    for (i, number1) in numbers.iter().enumerate() {
-        for (j, number2) in numbers[(i + 1)..].iter().enumerate() {
-            for number3 in numbers[(i + j + 2)..].iter() {
-                if number1 + number2 + number3 == 2020 {
-                    println!("{}", number1 * number2 * number3)
+fn k_sum(numbers: &[u32], target: u32, k: usize, duplicates: Duplicates) -> Option<KSum> {
+    k_sum_sorted(&sorted_entries(numbers, duplicates), target, k).map(KSum::from_entries)
+}
+
+fn run_end(entries: &[(usize, u32)], start: usize) -> usize {
+    let value = entries[start].1;
+    start
+        + entries[start..]
+            .iter()
+            .take_while(|&&(_, v)| v == value)
+            .count()
+}
+
+fn all_two_sums_sorted(entries: &[(usize, u32)], target: u32) -> Vec<Vec<(usize, u32)>> {
+    let mut found = vec![];
+    if entries.is_empty() {
+        return found;
+    }
+
+    let mut low = 0;
+    let mut high = entries.len() - 1;
+    while low < high {
+        let sum = entries[low].1 as u64 + entries[high].1 as u64;
+        match sum.cmp(&(target as u64)) {
+            Ordering::Less => low += 1,
+            Ordering::Greater => high -= 1,
+            Ordering::Equal if entries[low].1 == entries[high].1 => {
+                for a in low..=high {
+                    for b in (a + 1)..=high {
+                        found.push(vec![entries[a], entries[b]]);
+                    }
+                }
+                break;
+            }
+            Ordering::Equal => {
+                let low_end = run_end(entries, low);
+                let high_start = entries[..=high]
+                    .iter()
+                    .rposition(|&(_, v)| v != entries[high].1)
+                    .unwrap()
+                    + 1;
+                for a in low..low_end {
+                    for b in high_start..=high {
+                        found.push(vec![entries[a], entries[b]]);
+                    }
                 }
+                low = low_end;
+                high = high_start - 1;
             }
         }
     }
+    found
 }
 
-fn main() -> Result<()> {
+fn all_k_sums_sorted(entries: &[(usize, u32)], target: u32, k: usize) -> Vec<Vec<(usize, u32)>> {
+    match k {
+        0 if target == 0 => vec![vec![]],
+        0 => vec![],
+        1 => entries
+            .iter()
+            .filter(|&&(_, v)| v == target)
+            .map(|&e| vec![e])
+            .collect(),
+        2 => all_two_sums_sorted(entries, target),
+        _ => {
+            let mut found = vec![];
+            for (i, &entry) in entries.iter().enumerate() {
+                if entry.1 > target {
+                    break;
+                }
+                for mut rest in all_k_sums_sorted(&entries[(i + 1)..], target - entry.1, k - 1) {
+                    rest.insert(0, entry);
+                    found.push(rest);
+                }
+            }
+            found
+        }
+    }
+}
+
+fn all_k_sums(numbers: &[u32], target: u32, k: usize, duplicates: Duplicates) -> Vec<KSum> {
+    all_k_sums_sorted(&sorted_entries(numbers, duplicates), target, k)
+        .into_iter()
+        .map(KSum::from_entries)
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Options {
+    target: u32,
+    all: bool,
+    duplicates: Duplicates,
+}
+
+impl Options {
+    fn from_args(args: impl Iterator<Item = String>) -> Result<Self> {
+        let mut options = Self {
+            target: 2020,
+            all: false,
+            duplicates: Duplicates::Distinct,
+        };
+        for arg in args {
+            match arg.as_str() {
+                "--all" => options.all = true,
+                "--merge-duplicates" => options.duplicates = Duplicates::Merged,
+                a if a.starts_with("--") => {
+                    return Err(Error::Usage(format!("unknown option {}", a)))
+                }
+                a => options.target = a.parse()?,
+            }
+        }
+        Ok(options)
+    }
+}
+
+fn print_solution(found: &KSum) {
+    println!(
+        "{} (values {:?} at indices {:?})",
+        found.product(),
+        found.values,
+        found.indices
+    )
+}
+
+fn print_k_sum(numbers: &[u32], k: usize, options: &Options) {
+    let found = if options.all {
+        all_k_sums(numbers, options.target, k, options.duplicates)
+    } else {
+        k_sum(numbers, options.target, k, options.duplicates)
+            .into_iter()
+            .collect()
+    };
+
+    if found.is_empty() {
+        println!("no solution");
+    }
+    for f in &found {
+        print_solution(f);
+    }
+}
+
+fn part_one(numbers: &[u32], options: &Options) {
+    print_k_sum(numbers, 2, options);
+}
+
+fn part_two(numbers: &[u32], options: &Options) {
+    print_k_sum(numbers, 3, options);
+}
+
+fn run() -> Result<()> {
+    let options = Options::from_args(env::args().skip(1))?;
     let numbers: Vec<u32> = parse_lines(io::stdin().lock())?;
 
     println!("Part 1");
-    part_one(&numbers);
+    part_one(&numbers, &options);
 
     println!("Part 2");
-    part_two(&numbers);
+    part_two(&numbers, &options);
 
     Ok(())
 }
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
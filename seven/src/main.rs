@@ -1,9 +1,14 @@
 use combine::parser::char::{digit, letter, spaces, string};
 use combine::stream::{easy, position, Stream};
 use combine::{attempt, eof, many1, sep_by1, EasyParser, Parser};
-use std::collections::{HashMap, HashSet};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::env;
+use std::fmt;
 use std::io::{self, BufRead};
 use std::num;
+use std::process;
+use std::rc::Rc;
 use std::str::FromStr;
 
 macro_rules! parser_from_str {
@@ -25,6 +30,24 @@ enum Error {
     ParseInt(num::ParseIntError),
     Io(io::Error),
     ParseError(String),
+    DuplicateBag(String),
+    UndefinedBag(String),
+    Cycle(String),
+    UnknownBag(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ParseInt(e) => write!(f, "{}", e),
+            Self::Io(e) => write!(f, "{}", e),
+            Self::ParseError(e) => write!(f, "{}", e.trim_end()),
+            Self::DuplicateBag(name) => write!(f, "{} bags are defined more than once", name),
+            Self::UndefinedBag(e) => write!(f, "no rule for {}", e),
+            Self::Cycle(path) => write!(f, "bag rules contain a cycle: {}", path),
+            Self::UnknownBag(e) => write!(f, "{}", e),
+        }
+    }
 }
 
 impl From<io::Error> for Error {
@@ -47,21 +70,21 @@ impl From<easy::Errors<char, &str, position::SourcePosition>> for Error {
 
 type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct BagName(String);
 
+impl fmt::Display for BagName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 #[derive(Debug)]
 struct Bag {
     name: BagName,
     can_contain: Vec<(usize, BagName)>,
 }
 
-impl Bag {
-    fn can_contain(&self, bag_name: &BagName) -> bool {
-        self.can_contain.iter().find(|v| &v.1 == bag_name).is_some()
-    }
-}
-
 impl Bag {
     fn parser<Input>() -> impl Parser<Input, Output = Self>
     where
@@ -95,64 +118,568 @@ impl Bag {
 
 parser_from_str!(Bag);
 
-fn build_bag_map(bags: &[Bag]) -> HashMap<&BagName, &Bag> {
-    let mut map: HashMap<&BagName, &Bag> = HashMap::new();
-    for bag in bags {
-        assert!(map.insert(&bag.name, &bag).is_none());
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitute = previous[j] + (ca != cb) as usize;
+            current.push(substitute.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
     }
-    map
+    previous[b.len()]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Visit {
+    New,
+    OnPath,
+    Done,
 }
 
-fn bags_contain_bag<'a>(
-    bag_map: &'a HashMap<&'a BagName, &'a Bag>,
-    bag_name: &'a BagName,
-) -> HashSet<&'a BagName> {
-    let contain: HashSet<&BagName> = bag_map
-        .values()
-        .filter_map(|b| {
-            if b.can_contain(&bag_name) {
-                Some(&b.name)
+struct BagGraph {
+    names: Vec<BagName>,
+    ids: HashMap<BagName, usize>,
+    contains: Vec<Vec<(usize, usize)>>,
+    contained_in: Vec<Vec<usize>>,
+    ancestors: RefCell<Vec<Option<Rc<HashSet<usize>>>>>,
+    contained_counts: RefCell<Vec<Option<usize>>>,
+}
+
+impl BagGraph {
+    fn new(bags: Vec<Bag>) -> Result<Self> {
+        let mut graph = Self {
+            names: vec![],
+            ids: HashMap::new(),
+            contains: vec![],
+            contained_in: vec![],
+            ancestors: RefCell::new(vec![]),
+            contained_counts: RefCell::new(vec![]),
+        };
+        for bag in &bags {
+            if graph.ids.contains_key(&bag.name) {
+                return Err(Error::DuplicateBag(bag.name.0.clone()));
+            }
+            graph.add_node(&bag.name);
+        }
+        for bag in &bags {
+            let id = graph.ids[&bag.name];
+            for (count, name) in &bag.can_contain {
+                let child = *graph.ids.get(name).ok_or_else(|| {
+                    Error::UndefinedBag(format!("{} (contained in {})", name.0, bag.name.0))
+                })?;
+                graph.contains[id].push((*count, child));
+                graph.contained_in[child].push(id);
+            }
+        }
+        if let Some(cycle) = graph.find_cycle() {
+            let path: Vec<_> = cycle.iter().map(|&id| graph.names[id].0.as_str()).collect();
+            return Err(Error::Cycle(path.join(" -> ")));
+        }
+        graph.ancestors = RefCell::new(vec![None; graph.names.len()]);
+        graph.contained_counts = RefCell::new(vec![None; graph.names.len()]);
+        Ok(graph)
+    }
+
+    fn add_node(&mut self, name: &BagName) -> usize {
+        let id = self.names.len();
+        self.names.push(name.clone());
+        self.ids.insert(name.clone(), id);
+        self.contains.push(vec![]);
+        self.contained_in.push(vec![]);
+        id
+    }
+
+    fn find_cycle(&self) -> Option<Vec<usize>> {
+        let mut visited = vec![Visit::New; self.names.len()];
+        for root in 0..self.names.len() {
+            if visited[root] != Visit::New {
+                continue;
+            }
+            visited[root] = Visit::OnPath;
+            let mut stack = vec![(root, 0)];
+            while let Some((id, next)) = stack.last().copied() {
+                let (_, child) = match self.contains[id].get(next) {
+                    Some(&edge) => edge,
+                    None => {
+                        visited[id] = Visit::Done;
+                        stack.pop();
+                        continue;
+                    }
+                };
+                stack.last_mut().unwrap().1 += 1;
+                match visited[child] {
+                    Visit::New => {
+                        visited[child] = Visit::OnPath;
+                        stack.push((child, 0));
+                    }
+                    Visit::OnPath => {
+                        let start = stack.iter().position(|&(p, _)| p == child).unwrap();
+                        let mut cycle: Vec<_> = stack[start..].iter().map(|&(p, _)| p).collect();
+                        cycle.push(child);
+                        return Some(cycle);
+                    }
+                    Visit::Done => {}
+                }
+            }
+        }
+        None
+    }
+
+    fn ancestor_ids(&self, id: usize) -> Rc<HashSet<usize>> {
+        if let Some(ancestors) = &self.ancestors.borrow()[id] {
+            return ancestors.clone();
+        }
+        let mut ancestors = HashSet::new();
+        let mut stack = vec![id];
+        while let Some(next) = stack.pop() {
+            for &parent in &self.contained_in[next] {
+                if ancestors.insert(parent) {
+                    stack.push(parent);
+                }
+            }
+        }
+        let ancestors = Rc::new(ancestors);
+        self.ancestors.borrow_mut()[id] = Some(ancestors.clone());
+        ancestors
+    }
+
+    fn descendant_ids(&self, id: usize) -> HashSet<usize> {
+        let mut descendants = HashSet::new();
+        let mut stack = vec![id];
+        while let Some(next) = stack.pop() {
+            for &(_, child) in &self.contains[next] {
+                if descendants.insert(child) {
+                    stack.push(child);
+                }
+            }
+        }
+        descendants
+    }
+
+    fn contained_count_of(&self, id: usize) -> usize {
+        let mut counts = self.contained_counts.borrow_mut();
+        let mut stack = vec![(id, false)];
+        while let Some((next, expanded)) = stack.pop() {
+            if counts[next].is_some() {
+                continue;
+            }
+            if expanded {
+                let count = self.contains[next]
+                    .iter()
+                    .map(|&(n, child)| n * (1 + counts[child].unwrap()))
+                    .sum();
+                counts[next] = Some(count);
             } else {
-                None
+                stack.push((next, true));
+                for &(_, child) in &self.contains[next] {
+                    if counts[child].is_none() {
+                        stack.push((child, false));
+                    }
+                }
             }
+        }
+        counts[id].unwrap()
+    }
+
+    fn contents_of(&self, id: usize) -> Vec<Contents<'_>> {
+        let mut contents = vec![];
+        let mut stack: Vec<_> = self.contains[id]
+            .iter()
+            .rev()
+            .map(|&(count, child)| (count, child, 0, count))
+            .collect();
+        while let Some((count, id, depth, total)) = stack.pop() {
+            contents.push(Contents {
+                depth,
+                count,
+                total,
+                name: &self.names[id],
+            });
+            for &(n, child) in self.contains[id].iter().rev() {
+                stack.push((n, child, depth + 1, n * total));
+            }
+        }
+        contents
+    }
+
+    fn shortest_paths(&self, outer: usize, inner: usize, limit: usize) -> Vec<Vec<usize>> {
+        let mut distance = vec![None; self.names.len()];
+        distance[outer] = Some(0);
+        let mut queue = VecDeque::from(vec![outer]);
+        while let Some(id) = queue.pop_front() {
+            for &(_, child) in &self.contains[id] {
+                if distance[child].is_none() {
+                    distance[child] = Some(distance[id].unwrap() + 1);
+                    queue.push_back(child);
+                }
+            }
+        }
+        let mut paths = vec![];
+        if distance[inner].is_some() {
+            self.collect_shortest(inner, &distance, limit, &mut paths);
+        }
+        paths
+    }
+
+    fn collect_shortest(
+        &self,
+        inner: usize,
+        distance: &[Option<usize>],
+        limit: usize,
+        paths: &mut Vec<Vec<usize>>,
+    ) {
+        let mut stack = vec![(inner, 0)];
+        while let Some((id, next)) = stack.last().copied() {
+            if paths.len() >= limit {
+                return;
+            }
+            let d = distance[id].unwrap();
+            if d == 0 {
+                paths.push(stack.iter().rev().map(|&(p, _)| p).collect());
+                stack.pop();
+                continue;
+            }
+            let parents = &self.contained_in[id];
+            match parents[next..]
+                .iter()
+                .position(|&p| distance[p] == Some(d - 1))
+            {
+                Some(offset) => {
+                    stack.last_mut().unwrap().1 = next + offset + 1;
+                    stack.push((parents[next + offset], 0));
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    fn all_paths(&self, outer: usize, inner: usize, limit: usize) -> Vec<Vec<usize>> {
+        let ancestors = self.ancestor_ids(inner);
+        let mut paths = vec![];
+        if ancestors.contains(&outer) {
+            self.collect_all(outer, inner, &ancestors, limit, &mut paths);
+        }
+        paths
+    }
+
+    fn collect_all(
+        &self,
+        outer: usize,
+        inner: usize,
+        ancestors: &HashSet<usize>,
+        limit: usize,
+        paths: &mut Vec<Vec<usize>>,
+    ) {
+        let mut stack = vec![(outer, 0)];
+        while let Some((id, next)) = stack.last().copied() {
+            if paths.len() >= limit {
+                return;
+            }
+            let child = match self.contains[id].get(next) {
+                Some(&(_, child)) => child,
+                None => {
+                    stack.pop();
+                    continue;
+                }
+            };
+            stack.last_mut().unwrap().1 += 1;
+            if child == inner {
+                let mut path: Vec<_> = stack.iter().map(|&(p, _)| p).collect();
+                path.push(child);
+                paths.push(path);
+            } else if ancestors.contains(&child) {
+                stack.push((child, 0));
+            }
+        }
+    }
+
+    fn containment_paths(
+        &self,
+        outer: &BagName,
+        inner: &BagName,
+        search: PathSearch,
+    ) -> Result<(Vec<ContainmentPath<'_>>, bool)> {
+        let (outer, inner) = (self.id(outer)?, self.id(inner)?);
+        let mut paths = match search {
+            PathSearch::Shortest(limit) => self.shortest_paths(outer, inner, limit + 1),
+            PathSearch::All(limit) => self.all_paths(outer, inner, limit + 1),
+        };
+        let truncated = paths.len() > search.limit();
+        paths.truncate(search.limit());
+        let paths = paths
+            .into_iter()
+            .filter(|p| p.len() > 1)
+            .map(|p| ContainmentPath::new(self, &p))
+            .collect();
+        Ok((paths, truncated))
+    }
+
+    fn can_contain(&self, outer: &BagName, inner: &BagName) -> Result<bool> {
+        let outer = self.id(outer)?;
+        Ok(self.ancestor_ids(self.id(inner)?).contains(&outer))
+    }
+
+    fn suggestions(&self, name: &BagName) -> Vec<&BagName> {
+        let mut scored: Vec<(usize, &BagName)> = self
+            .names
+            .iter()
+            .map(|n| (edit_distance(&n.0, &name.0), n))
+            .filter(|&(distance, _)| distance <= name.0.len() / 3)
+            .collect();
+        scored.sort_by(|a, b| a.0.cmp(&b.0).then(a.1 .0.cmp(&b.1 .0)));
+        scored.into_iter().take(5).map(|(_, n)| n).collect()
+    }
+
+    fn id(&self, name: &BagName) -> Result<usize> {
+        if let Some(&id) = self.ids.get(name) {
+            return Ok(id);
+        }
+        let suggestions: Vec<_> = self
+            .suggestions(name)
+            .iter()
+            .map(|n| n.0.as_str())
+            .collect();
+        if suggestions.is_empty() {
+            Err(Error::UnknownBag(format!("no rule for \"{}\"", name)))
+        } else {
+            Err(Error::UnknownBag(format!(
+                "no rule for \"{}\", did you mean: {}",
+                name,
+                suggestions.join(", ")
+            )))
+        }
+    }
+
+    fn ancestors(&self, name: &BagName) -> Result<HashSet<&BagName>> {
+        Ok(self
+            .ancestor_ids(self.id(name)?)
+            .iter()
+            .map(|&a| &self.names[a])
+            .collect())
+    }
+
+    fn contained_count(&self, name: &BagName) -> Result<usize> {
+        Ok(self.contained_count_of(self.id(name)?))
+    }
+
+    fn contents(&self, name: &BagName) -> Result<Vec<Contents<'_>>> {
+        Ok(self.contents_of(self.id(name)?))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DotScope {
+    All,
+    From,
+    To,
+}
+
+impl FromStr for DotScope {
+    type Err = Error;
+    fn from_str(input: &str) -> Result<Self> {
+        match input {
+            "all" => Ok(Self::All),
+            "from" => Ok(Self::From),
+            "to" => Ok(Self::To),
+            _ => Err(Error::ParseError(format!("unknown dot scope {}", input))),
+        }
+    }
+}
+
+struct Dot<'a> {
+    graph: &'a BagGraph,
+    nodes: Vec<usize>,
+    highlight: Option<usize>,
+}
+
+impl<'a> Dot<'a> {
+    fn new(graph: &'a BagGraph, scope: DotScope, bag: &BagName) -> Result<Self> {
+        let (mut nodes, highlight): (Vec<usize>, _) = match scope {
+            DotScope::All => ((0..graph.names.len()).collect(), None),
+            DotScope::From => {
+                let id = graph.id(bag)?;
+                (graph.descendant_ids(id).into_iter().collect(), Some(id))
+            }
+            DotScope::To => {
+                let id = graph.id(bag)?;
+                (graph.ancestor_ids(id).iter().copied().collect(), Some(id))
+            }
+        };
+        if let Some(id) = highlight {
+            nodes.push(id);
+        }
+        nodes.sort_unstable();
+        nodes.dedup();
+        Ok(Self {
+            graph,
+            nodes,
+            highlight,
         })
-        .collect();
-    let mut more_contain: HashSet<&BagName> = HashSet::new();
-    for &c in contain.iter() {
-        more_contain = more_contain
-            .union(&bags_contain_bag(bag_map, c))
-            .cloned()
+    }
+}
+
+impl fmt::Display for Dot<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "digraph bags {{")?;
+        for &id in &self.nodes {
+            let name = &self.graph.names[id].0;
+            if self.highlight == Some(id) {
+                writeln!(f, "    {:?} [style=filled];", name)?;
+            } else {
+                writeln!(f, "    {:?};", name)?;
+            }
+        }
+        for &id in &self.nodes {
+            for &(count, child) in &self.graph.contains[id] {
+                if self.nodes.binary_search(&child).is_ok() {
+                    writeln!(
+                        f,
+                        "    {:?} -> {:?} [label=\"{}\"];",
+                        self.graph.names[id].0, self.graph.names[child].0, count
+                    )?;
+                }
+            }
+        }
+        write!(f, "}}")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PathSearch {
+    Shortest(usize),
+    All(usize),
+}
+
+impl PathSearch {
+    const DEFAULT_LIMIT: usize = 100;
+
+    fn limit(self) -> usize {
+        match self {
+            Self::Shortest(limit) | Self::All(limit) => limit,
+        }
+    }
+}
+
+fn parse_path_limit(flag: &str, limit: &str) -> Result<usize> {
+    match limit.parse()? {
+        0 => Err(Error::ParseError(format!(
+            "{} limit must be at least 1",
+            flag
+        ))),
+        limit => Ok(limit),
+    }
+}
+
+struct ContainmentPath<'a> {
+    outer: &'a BagName,
+    steps: Vec<(usize, &'a BagName)>,
+}
+
+impl<'a> ContainmentPath<'a> {
+    fn new(graph: &'a BagGraph, path: &[usize]) -> Self {
+        let steps = path
+            .windows(2)
+            .map(|w| {
+                let &(count, _) = graph.contains[w[0]]
+                    .iter()
+                    .find(|&&(_, child)| child == w[1])
+                    .unwrap();
+                (count, &graph.names[w[1]])
+            })
             .collect();
+        Self {
+            outer: &graph.names[path[0]],
+            steps,
+        }
+    }
+}
+
+impl fmt::Display for ContainmentPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.outer)?;
+        for (count, name) in &self.steps {
+            write!(f, " -> {} {}", count, name)?;
+        }
+        Ok(())
     }
-    contain.union(&more_contain).cloned().collect()
 }
 
-fn part_one(bags: &[Bag]) {
-    let bag_map = build_bag_map(bags);
-    println!(
-        "{}",
-        bags_contain_bag(&bag_map, &BagName("shiny gold".into())).len()
-    );
+struct Contents<'a> {
+    depth: usize,
+    count: usize,
+    total: usize,
+    name: &'a BagName,
+}
+
+impl Contents<'_> {
+    const MAX_INDENT: usize = 32;
 }
 
-fn bag_must_contain<'a>(
-    bag_map: &'a HashMap<&'a BagName, &'a Bag>,
-    bag_name: &'a BagName,
-) -> usize {
-    let can_contain = &bag_map.get(bag_name).unwrap().can_contain;
-    let mut total = 0;
-    for (num, name) in can_contain {
-        total += num * (1 + bag_must_contain(bag_map, name));
+impl fmt::Display for Contents<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:indent$}",
+            "",
+            indent = self.depth.min(Self::MAX_INDENT) * 2
+        )?;
+        if self.depth > Self::MAX_INDENT {
+            write!(f, "[depth {}] ", self.depth)?;
+        }
+        write!(f, "{} {} ({} total)", self.count, self.name, self.total)
     }
-    total
 }
 
-fn part_two(bags: &[Bag]) {
-    let bag_map = build_bag_map(bags);
-    println!(
-        "{}",
-        bag_must_contain(&bag_map, &BagName("shiny gold".into()))
-    );
+fn part_one(graph: &BagGraph, bag: &BagName) -> Result<()> {
+    println!("{}", graph.ancestors(bag)?.len());
+    Ok(())
+}
+
+fn part_two(graph: &BagGraph, bag: &BagName) -> Result<()> {
+    println!("{}", graph.contained_count(bag)?);
+    Ok(())
+}
+
+fn print_containers(graph: &BagGraph, bag: &BagName) -> Result<()> {
+    let mut containers: Vec<_> = graph.ancestors(bag)?.into_iter().collect();
+    containers.sort_by(|a, b| a.0.cmp(&b.0));
+    for c in containers {
+        println!("{}", c);
+    }
+    Ok(())
+}
+
+fn print_explanation(
+    graph: &BagGraph,
+    outer: &BagName,
+    bag: &BagName,
+    search: PathSearch,
+) -> Result<()> {
+    if !graph.can_contain(outer, bag)? {
+        println!("{} cannot contain {}", outer, bag);
+        return Ok(());
+    }
+    let (paths, truncated) = graph.containment_paths(outer, bag, search)?;
+    for p in &paths {
+        println!("{}", p);
+    }
+    if truncated {
+        println!("stopped after {} paths", paths.len());
+    }
+    Ok(())
+}
+
+fn print_contents(graph: &BagGraph, bag: &BagName) -> Result<()> {
+    for c in graph.contents(bag)? {
+        println!("{}", c);
+    }
+    println!("{} bags in total", graph.contained_count(bag)?);
+    Ok(())
 }
 
 fn parse_lines<R: BufRead, T: FromStr>(lines: R) -> Result<Vec<T>>
@@ -166,14 +693,71 @@ where
     Ok(values)
 }
 
-fn main() -> Result<()> {
+fn parse_bag_name(name: &str) -> BagName {
+    BagName(name.split_whitespace().collect::<Vec<_>>().join(" "))
+}
+
+fn run() -> Result<()> {
+    let mut bag = BagName("shiny gold".into());
+    let mut show_containers = false;
+    let mut show_contents = false;
+    let mut dot = None;
+    let mut explain = None;
+    let mut search = PathSearch::Shortest(PathSearch::DEFAULT_LIMIT);
+    for arg in env::args().skip(1) {
+        match arg.splitn(2, '=').collect::<Vec<_>>()[..] {
+            ["--bag", name] => bag = parse_bag_name(name),
+            ["--containers"] => show_containers = true,
+            ["--contents"] => show_contents = true,
+            ["--dot"] => dot = Some(DotScope::All),
+            ["--explain", outer] => explain = Some(parse_bag_name(outer)),
+            ["--shortest-paths", limit] => {
+                search = PathSearch::Shortest(parse_path_limit("--shortest-paths", limit)?)
+            }
+            ["--all-paths", limit] => {
+                search = PathSearch::All(parse_path_limit("--all-paths", limit)?)
+            }
+            ["--dot", scope] => dot = Some(scope.parse()?),
+            _ => return Err(Error::ParseError(format!("unknown argument {}", arg))),
+        }
+    }
+
     let bags: Vec<Bag> = parse_lines(io::stdin().lock())?;
+    let graph = BagGraph::new(bags)?;
+
+    if let Some(outer) = explain {
+        return print_explanation(&graph, &outer, &bag, search);
+    }
+
+    if let Some(scope) = dot {
+        println!("{}", Dot::new(&graph, scope, &bag)?);
+        return Ok(());
+    }
+
+    if show_containers || show_contents {
+        if show_containers {
+            print_containers(&graph, &bag)?;
+        }
+        if show_contents {
+            print_contents(&graph, &bag)?;
+        }
+        return Ok(());
+    }
+
+    graph.id(&bag)?;
 
     println!("Part 1");
-    part_one(&bags);
+    part_one(&graph, &bag)?;
 
     println!("Part 2");
-    part_two(&bags);
+    part_two(&graph, &bag)?;
 
     Ok(())
 }
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
@@ -1,9 +1,12 @@
 use combine::parser::char::{char, letter};
 use combine::stream::Stream;
 use combine::{many1, sep_by, sep_end_by, EasyParser, Parser};
-use std::collections::HashSet;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
 use std::io::{self, Read};
 use std::num;
+use std::ops;
 use std::str::FromStr;
 
 fn require_no_remaining(remaining: &str) -> Result<()> {
@@ -14,19 +17,6 @@ fn require_no_remaining(remaining: &str) -> Result<()> {
     }
 }
 
-macro_rules! parser_from_str {
-    ($s:ident) => {
-        impl FromStr for $s {
-            type Err = Error;
-            fn from_str(input: &str) -> Result<Self> {
-                let (p, remaining): (Self, &str) = Self::parser().easy_parse(input)?;
-                require_no_remaining(remaining)?;
-                Ok(p)
-            }
-        }
-    };
-}
-
 #[derive(Debug)]
 enum Error {
     ParseInt(num::ParseIntError),
@@ -56,83 +46,431 @@ impl From<combine::easy::ParseError<&str>> for Error {
 type Result<T> = std::result::Result<T, Error>;
 
 #[derive(Debug)]
-struct Answer(HashSet<char>);
+struct Alphabet(Vec<char>);
+
+impl Alphabet {
+    const LATIN: usize = 26;
+
+    fn from_input(input: &str) -> Self {
+        let mut letters: Vec<char> = ('a'..='z').collect();
+        let mut extra: Vec<char> = input
+            .chars()
+            .filter(|c| c.is_alphabetic() && !c.is_ascii_lowercase())
+            .collect();
+        extra.sort_unstable();
+        extra.dedup();
+        letters.extend(extra);
+        Self(letters)
+    }
+
+    fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    fn index(&self, question: char) -> Option<usize> {
+        if question.is_ascii_lowercase() {
+            Some(question as usize - 'a' as usize)
+        } else {
+            self.0[Self::LATIN..]
+                .binary_search(&question)
+                .ok()
+                .map(|i| i + Self::LATIN)
+        }
+    }
+
+    fn question(&self, index: usize) -> char {
+        self.0[index]
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct QuestionSet(Vec<u64>);
+
+impl QuestionSet {
+    const WORD_BITS: usize = 64;
+
+    fn empty(width: usize) -> Self {
+        Self(vec![0; width.div_ceil(Self::WORD_BITS)])
+    }
+
+    fn insert(&mut self, index: usize) {
+        self.0[index / Self::WORD_BITS] |= 1 << (index % Self::WORD_BITS);
+    }
+
+    fn contains(&self, index: usize) -> bool {
+        self.0
+            .get(index / Self::WORD_BITS)
+            .is_some_and(|w| w & (1 << (index % Self::WORD_BITS)) != 0)
+    }
+
+    fn len(&self) -> usize {
+        self.0.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.0.len() * Self::WORD_BITS).filter(move |&i| self.contains(i))
+    }
+}
+
+impl ops::BitOr for &QuestionSet {
+    type Output = QuestionSet;
+
+    fn bitor(self, other: Self) -> QuestionSet {
+        debug_assert_eq!(self.0.len(), other.0.len());
+        QuestionSet(self.0.iter().zip(&other.0).map(|(a, b)| a | b).collect())
+    }
+}
+
+impl ops::BitAnd for &QuestionSet {
+    type Output = QuestionSet;
+
+    fn bitand(self, other: Self) -> QuestionSet {
+        debug_assert_eq!(self.0.len(), other.0.len());
+        QuestionSet(self.0.iter().zip(&other.0).map(|(a, b)| a & b).collect())
+    }
+}
+
+#[derive(Debug)]
+struct Answer(QuestionSet);
 
 impl Answer {
-    fn parser<Input>() -> impl Parser<Input, Output = Self>
+    fn parser<'a, Input>(alphabet: &'a Alphabet) -> impl Parser<Input, Output = Self> + 'a
     where
-        Input: Stream<Token = char>,
+        Input: Stream<Token = char> + 'a,
     {
-        many1(letter()).map(|v: Vec<char>| Self(v.iter().cloned().collect()))
+        many1(letter()).map(move |v: Vec<char>| {
+            let mut questions = QuestionSet::empty(alphabet.len());
+            for c in v {
+                questions.insert(alphabet.index(c).unwrap());
+            }
+            Self(questions)
+        })
     }
 }
 
 #[derive(Debug)]
-struct Group(Vec<Answer>);
+struct Group {
+    answers: Vec<Answer>,
+    width: usize,
+}
 
 impl Group {
-    fn parser<Input>() -> impl Parser<Input, Output = Self>
+    fn parser<'a, Input>(alphabet: &'a Alphabet) -> impl Parser<Input, Output = Self> + 'a
     where
-        Input: Stream<Token = char>,
+        Input: Stream<Token = char> + 'a,
     {
-        sep_end_by(Answer::parser(), char('\n')).map(|v: Vec<_>| Self(v))
+        sep_end_by(Answer::parser(alphabet), char('\n')).map(move |answers: Vec<_>| Self {
+            answers,
+            width: alphabet.len(),
+        })
+    }
+
+    fn anyone(&self) -> QuestionSet {
+        self.answers
+            .iter()
+            .fold(QuestionSet::empty(self.width), |all, a| &all | &a.0)
+    }
+
+    fn everyone(&self) -> QuestionSet {
+        let mut answers = self.answers.iter().map(|a| a.0.clone());
+        let first = answers
+            .next()
+            .unwrap_or_else(|| QuestionSet::empty(self.width));
+        answers.fold(first, |all, a| &all & &a)
     }
 
     fn anyone_yes_count(&self) -> usize {
-        let mut all = HashSet::new();
-        for a in &self.0 {
-            all = all.union(&a.0).cloned().collect();
-        }
-        all.len()
+        self.anyone().len()
     }
 
     fn everyone_yes_count(&self) -> usize {
-        let mut all = self.0[0].0.clone();
-        for a in &self.0 {
-            all = all.intersection(&a.0).cloned().collect();
-        }
-        all.len()
+        self.everyone().len()
+    }
+
+    fn people(&self) -> usize {
+        self.answers.len()
+    }
+
+    fn yes_count(&self, question: usize) -> usize {
+        self.answers
+            .iter()
+            .filter(|a| a.0.contains(question))
+            .count()
+    }
+
+    fn matching_questions<'a>(
+        &'a self,
+        query: &'a Query,
+        questions: &'a QuestionSet,
+        alphabet: &'a Alphabet,
+    ) -> impl Iterator<Item = usize> + 'a {
+        questions
+            .iter()
+            .filter(move |&q| query.matches(alphabet.question(q), self.yes_count(q), self.people()))
     }
 }
 
-parser_from_str!(Group);
+#[derive(Debug)]
+struct QuestionStats {
+    question: char,
+    groups: usize,
+    people: usize,
+    unanimous: usize,
+}
+
+impl fmt::Display for QuestionStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} groups, {} people, {} unanimous",
+            self.question, self.groups, self.people, self.unanimous
+        )
+    }
+}
 
 #[derive(Debug)]
-struct GroupCollection(Vec<Group>);
+struct GroupCollection {
+    alphabet: Alphabet,
+    groups: Vec<Group>,
+}
 
 impl GroupCollection {
-    fn parser<Input>() -> impl Parser<Input, Output = Self>
-    where
-        Input: Stream<Token = char>,
-    {
-        sep_by(Group::parser(), char('\n')).map(|v| Self(v))
+    fn questions(&self) -> QuestionSet {
+        self.groups
+            .iter()
+            .fold(QuestionSet::empty(self.alphabet.len()), |all, g| {
+                &all | &g.anyone()
+            })
+    }
+
+    fn count(&self, query: &Query) -> usize {
+        let questions = self.questions();
+        self.groups
+            .iter()
+            .map(|g| {
+                g.matching_questions(query, &questions, &self.alphabet)
+                    .count()
+            })
+            .sum()
+    }
+
+    fn tally(&self, query: &Query) -> BTreeMap<char, usize> {
+        let questions = self.questions();
+        let mut tally: BTreeMap<char, usize> = questions
+            .iter()
+            .map(|q| (self.alphabet.question(q), 0))
+            .collect();
+        for g in &self.groups {
+            for q in g.matching_questions(query, &questions, &self.alphabet) {
+                *tally.get_mut(&self.alphabet.question(q)).unwrap() += 1;
+            }
+        }
+        tally
+    }
+
+    fn stats(&self) -> Vec<QuestionStats> {
+        let questions = self.questions();
+        let mut stats: Vec<_> = questions
+            .iter()
+            .map(|q| QuestionStats {
+                question: self.alphabet.question(q),
+                groups: 0,
+                people: 0,
+                unanimous: 0,
+            })
+            .collect();
+        for g in &self.groups {
+            let (anyone, everyone) = (g.anyone(), g.everyone());
+            for (s, q) in stats.iter_mut().zip(questions.iter()) {
+                s.groups += anyone.contains(q) as usize;
+                s.people += g.yes_count(q);
+                s.unanimous += everyone.contains(q) as usize;
+            }
+        }
+        stats
     }
 }
 
 impl FromStr for GroupCollection {
     type Err = Error;
     fn from_str(input: &str) -> Result<Self> {
-        let (p, remaining): (Self, &str) = Self::parser().easy_parse(input)?;
+        let alphabet = Alphabet::from_input(input);
+        let (mut groups, remaining): (Vec<Group>, _) =
+            sep_by(Group::parser(&alphabet), char('\n')).easy_parse(input)?;
         require_no_remaining(remaining)?;
-        Ok(p)
+        groups.retain(|g| g.people() > 0);
+        Ok(Self { alphabet, groups })
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Query {
+    Anyone,
+    Everyone,
+    Majority,
+    AtLeast(usize),
+    AtMost(usize),
+    Exactly(usize),
+    Question(char),
+    Not(Box<Query>),
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+}
+
+impl Query {
+    fn matches(&self, question: char, yes: usize, people: usize) -> bool {
+        match self {
+            Self::Anyone => yes > 0,
+            Self::Everyone => yes == people,
+            Self::Majority => yes * 2 > people,
+            Self::AtLeast(k) => yes >= *k,
+            Self::AtMost(k) => yes <= *k,
+            Self::Exactly(k) => yes == *k,
+            Self::Question(q) => question == *q,
+            Self::Not(a) => !a.matches(question, yes, people),
+            Self::And(a, b) => a.matches(question, yes, people) && b.matches(question, yes, people),
+            Self::Or(a, b) => a.matches(question, yes, people) || b.matches(question, yes, people),
+        }
+    }
+}
+
+struct QueryParser<'a> {
+    tokens: std::iter::Peekable<std::vec::IntoIter<&'a str>>,
+}
+
+impl<'a> QueryParser<'a> {
+    fn next_token(&mut self) -> Result<&'a str> {
+        self.tokens
+            .next()
+            .ok_or_else(|| Error::ParseError("unexpected end of query".into()))
+    }
+
+    fn number(&mut self) -> Result<usize> {
+        Ok(self.next_token()?.parse()?)
+    }
+
+    fn or(&mut self) -> Result<Query> {
+        let mut query = self.and()?;
+        while self.tokens.peek() == Some(&"or") {
+            self.tokens.next();
+            query = Query::Or(Box::new(query), Box::new(self.and()?));
+        }
+        Ok(query)
+    }
+
+    fn and(&mut self) -> Result<Query> {
+        let mut query = self.not()?;
+        while self.tokens.peek() == Some(&"and") {
+            self.tokens.next();
+            query = Query::And(Box::new(query), Box::new(self.not()?));
+        }
+        Ok(query)
+    }
+
+    fn not(&mut self) -> Result<Query> {
+        if self.tokens.peek() == Some(&"not") {
+            self.tokens.next();
+            return Ok(Query::Not(Box::new(self.not()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Query> {
+        Ok(match self.next_token()? {
+            "(" => {
+                let query = self.or()?;
+                match self.next_token()? {
+                    ")" => query,
+                    t => return Err(Error::ParseError(format!("expected ), got {}", t))),
+                }
+            }
+            "anyone" => Query::Anyone,
+            "everyone" => Query::Everyone,
+            "majority" => Query::Majority,
+            "at-least" => Query::AtLeast(self.number()?),
+            "at-most" => Query::AtMost(self.number()?),
+            "exactly" => Query::Exactly(self.number()?),
+            "question" => {
+                let token = self.next_token()?;
+                let mut chars = token.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => Query::Question(c),
+                    _ => return Err(Error::ParseError(format!("bad question {}", token))),
+                }
+            }
+            t => return Err(Error::ParseError(format!("unknown query term {}", t))),
+        })
+    }
+}
+
+impl FromStr for Query {
+    type Err = Error;
+    fn from_str(input: &str) -> Result<Self> {
+        let spaced = input.replace('(', " ( ").replace(')', " ) ");
+        let mut parser = QueryParser {
+            tokens: spaced
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .into_iter()
+                .peekable(),
+        };
+        let query = parser.or()?;
+        let remaining: Vec<_> = parser.tokens.collect();
+        require_no_remaining(&remaining.join(" "))?;
+        Ok(query)
     }
 }
 
 fn part_one(groups: &GroupCollection) {
-    let answer: usize = groups.0.iter().map(|g| g.anyone_yes_count()).sum();
+    let answer: usize = groups.groups.iter().map(|g| g.anyone_yes_count()).sum();
     println!("{}", answer);
 }
 
 fn part_two(groups: &GroupCollection) {
-    let answer: usize = groups.0.iter().map(|g| g.everyone_yes_count()).sum();
+    let answer: usize = groups.groups.iter().map(|g| g.everyone_yes_count()).sum();
     println!("{}", answer);
 }
 
 fn main() -> Result<()> {
+    let mut tally = false;
+    let mut stats = false;
+    let mut expression = vec![];
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--tally" => tally = true,
+            "--stats" => stats = true,
+            _ => expression.push(arg),
+        }
+    }
+    let query: Option<Query> = if expression.is_empty() {
+        None
+    } else {
+        Some(expression.join(" ").parse()?)
+    };
+
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
     let groups: GroupCollection = input.parse()?;
 
+    if stats {
+        for s in groups.stats() {
+            println!("{}", s);
+        }
+        return Ok(());
+    }
+
+    if tally || query.is_some() {
+        let query = query.unwrap_or(Query::Anyone);
+        if tally {
+            for (question, groups) in groups.tally(&query) {
+                println!("{}: {}", question, groups);
+            }
+        } else {
+            println!("{}", groups.count(&query));
+        }
+        return Ok(());
+    }
+
     println!("Part 1");
     part_one(&groups);
 
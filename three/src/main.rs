@@ -1,4 +1,9 @@
-use std::io::{self, BufRead};
+use std::collections::HashSet;
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+use std::ops::RangeInclusive;
 use std::str::FromStr;
 
 #[derive(Debug)]
@@ -43,6 +48,15 @@ impl FromStr for Tile {
     }
 }
 
+impl fmt::Display for Tile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Nothing => write!(f, "."),
+            Self::Tree => write!(f, "#"),
+        }
+    }
+}
+
 struct Row(Vec<Tile>);
 
 impl FromStr for Row {
@@ -66,39 +80,348 @@ impl Field {
         self.rows.len()
     }
 
+    fn width(&self) -> usize {
+        self.rows.first().map_or(0, |r| r.0.len())
+    }
+
     fn get(&self, x: usize, y: usize) -> Tile {
         let row = &self.rows[y];
         row.0[x % row.0.len()]
     }
+
+    fn tile_at(&self, x: isize, y: usize, wrap: bool) -> Option<Tile> {
+        let width = self.width() as isize;
+        if width == 0 {
+            None
+        } else if wrap {
+            Some(self.get(x.rem_euclid(width) as usize, y))
+        } else if (0..width).contains(&x) {
+            Some(self.get(x as usize, y))
+        } else {
+            None
+        }
+    }
+
+    fn path(&self, walk: &Walk) -> Vec<(isize, usize)> {
+        let Slope { right, down } = walk.slope;
+        let mut points = vec![];
+        match walk.stepping {
+            Stepping::Lattice => {
+                let (mut x, mut y) = (0, 0);
+                while y < self.height() {
+                    points.push((x, y));
+                    x += right;
+                    y += down;
+                }
+            }
+            Stepping::Rasterized if right.unsigned_abs() <= down => {
+                for y in 0..self.height() {
+                    points.push((round_div(y as isize * right, down as isize), y));
+                }
+            }
+            Stepping::Rasterized => {
+                let run = right.abs();
+                for k in 0.. {
+                    let y = round_div(k * down as isize, run) as usize;
+                    if y >= self.height() {
+                        break;
+                    }
+                    points.push((k * right.signum(), y));
+                }
+            }
+        }
+        points
+    }
+
+    fn search_slopes(&self, search: &SlopeSearch) -> Option<SearchResult> {
+        if self.width() == 0 {
+            return None;
+        }
+        let mut best: Option<SearchResult> = None;
+        for down in search.down.clone() {
+            for right in search.right.clone() {
+                let slope = Slope::new(right, down);
+                let walk = Walk {
+                    slope,
+                    stepping: search.stepping,
+                    wrap: search.wrap,
+                };
+                let trees = count_trees(self, &walk);
+                match &mut best {
+                    Some(b) if b.trees == trees => b.slopes.push(slope),
+                    Some(b) if !search.objective.is_better(trees, b.trees) => {}
+                    _ => {
+                        best = Some(SearchResult {
+                            trees,
+                            slopes: vec![slope],
+                        })
+                    }
+                }
+            }
+        }
+        best
+    }
 }
 
-fn count_trees(field: &Field, slope: (usize, usize)) -> usize {
-    let mut trees = 0;
-    let (mut x, mut y) = (0, 0);
-    while y < field.height() {
-        if field.get(x, y) == Tile::Tree {
-            trees += 1;
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in &self.rows {
+            for t in &row.0 {
+                write!(f, "{}", t)?;
+            }
+            writeln!(f)?;
         }
-        x += slope.0;
-        y += slope.1;
+        Ok(())
     }
-    trees
+}
+
+struct PathOverlay<'a> {
+    field: &'a Field,
+    walk: Walk,
+}
+
+impl fmt::Display for PathOverlay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let field = self.field;
+        let wrap = self.walk.wrap;
+        let hits: HashSet<(isize, usize)> = field
+            .path(&self.walk)
+            .into_iter()
+            .take_while(|&(x, y)| field.tile_at(x, y, wrap).is_some())
+            .collect();
+
+        let width = field.width() as isize;
+        let (mut start, mut end) = (0, width);
+        if wrap {
+            for &(x, _) in &hits {
+                start = start.min(x.div_euclid(width) * width);
+                end = end.max((x.div_euclid(width) + 1) * width);
+            }
+        }
+
+        for y in 0..field.height() {
+            for x in start..end {
+                let tile = field.tile_at(x, y, true).unwrap();
+                match (hits.contains(&(x, y)), tile) {
+                    (true, Tile::Nothing) => write!(f, "O")?,
+                    (true, Tile::Tree) => write!(f, "X")?,
+                    (false, t) => write!(f, "{}", t)?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+fn round_div(n: isize, d: isize) -> isize {
+    (2 * n + d).div_euclid(2 * d)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Slope {
+    right: isize,
+    down: usize,
+}
+
+impl fmt::Display for Slope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.right, self.down)
+    }
+}
+
+impl Slope {
+    fn new(right: isize, down: usize) -> Self {
+        assert!(down > 0, "slope must move down");
+        Self { right, down }
+    }
+}
+
+impl FromStr for Slope {
+    type Err = Error;
+    fn from_str(input: &str) -> Result<Self> {
+        let bad_slope = || Error::Parse(format!("bad slope {}", input));
+        let mut parts = input.splitn(2, '/');
+        let right = parts.next().unwrap().parse().map_err(|_| bad_slope())?;
+        let down = parts
+            .next()
+            .ok_or_else(bad_slope)?
+            .parse()
+            .map_err(|_| bad_slope())?;
+        if down == 0 {
+            return Err(bad_slope());
+        }
+        Ok(Self::new(right, down))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stepping {
+    Lattice,
+    Rasterized,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Walk {
+    slope: Slope,
+    stepping: Stepping,
+    wrap: bool,
+}
+
+impl Walk {
+    fn new(slope: Slope) -> Self {
+        Self {
+            slope,
+            stepping: Stepping::Lattice,
+            wrap: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Objective {
+    Fewest,
+    Most,
+}
+
+impl Objective {
+    fn is_better(&self, trees: usize, best: usize) -> bool {
+        match self {
+            Self::Fewest => trees < best,
+            Self::Most => trees > best,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SlopeSearch {
+    right: RangeInclusive<isize>,
+    down: RangeInclusive<usize>,
+    stepping: Stepping,
+    wrap: bool,
+    objective: Objective,
+}
+
+#[derive(Debug)]
+struct SearchResult {
+    trees: usize,
+    slopes: Vec<Slope>,
+}
+
+fn parse_range<T: FromStr + Copy>(input: &str) -> Result<RangeInclusive<T>> {
+    let bad_range = || Error::Parse(format!("bad range {}", input));
+    let mut parts = input.splitn(2, "..=");
+    let start = parts.next().unwrap().parse().map_err(|_| bad_range())?;
+    let end = match parts.next() {
+        Some(end) => end.parse().map_err(|_| bad_range())?,
+        None => start,
+    };
+    Ok(start..=end)
+}
+
+fn count_trees(field: &Field, walk: &Walk) -> usize {
+    field
+        .path(walk)
+        .into_iter()
+        .map_while(|(x, y)| field.tile_at(x, y, walk.wrap))
+        .filter(|&t| t == Tile::Tree)
+        .count()
 }
 
 fn part_one(field: &Field) {
-    println!("{}", count_trees(field, (3, 1)));
+    println!("{}", count_trees(field, &Walk::new(Slope::new(3, 1))));
 }
 
 fn part_two(field: &Field) {
     let slopes = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)];
-    let answer: usize = slopes.iter().map(|&s| count_trees(field, s)).product();
+    let answer: usize = slopes
+        .iter()
+        .map(|&(right, down)| count_trees(field, &Walk::new(Slope::new(right, down))))
+        .product();
     println!("{}", answer);
 }
 
 fn main() -> Result<()> {
+    let mut walks = vec![];
+    let mut stepping = Stepping::Lattice;
+    let mut wrap = true;
+    let mut objective = None;
+    let mut right_range = None;
+    let mut down_range = 1..=8;
+    let mut render = false;
+    let mut output = None;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--rasterize" => stepping = Stepping::Rasterized,
+            "--no-wrap" => wrap = false,
+            "--fewest" => objective = Some(Objective::Fewest),
+            "--most" => objective = Some(Objective::Most),
+            "--render" => render = true,
+            a if a.starts_with("--output=") => {
+                render = true;
+                output = Some(a[9..].to_owned());
+            }
+            a if a.starts_with("--right=") => right_range = Some(parse_range(&a[8..])?),
+            a if a.starts_with("--down=") => down_range = parse_range(&a[7..])?,
+            slope => walks.push(Walk::new(slope.parse()?)),
+        }
+    }
+    if down_range.contains(&0) {
+        return Err(Error::Parse("slope must move down".into()));
+    }
+
     let rows: Vec<Row> = parse_lines(io::stdin().lock())?;
     let field = Field { rows };
 
+    if let Some(objective) = objective {
+        let width = field.width() as isize;
+        let search = SlopeSearch {
+            right: right_range.unwrap_or(-width..=width),
+            down: down_range,
+            stepping,
+            wrap,
+            objective,
+        };
+        match field.search_slopes(&search) {
+            Some(result) => {
+                let slopes: Vec<_> = result.slopes.iter().map(|s| s.to_string()).collect();
+                println!("{} trees: {}", result.trees, slopes.join(", "));
+            }
+            None => println!("no slopes to search"),
+        }
+        return Ok(());
+    }
+
+    if render {
+        let mut out: Box<dyn Write> = match &output {
+            Some(path) => Box::new(File::create(path)?),
+            None => Box::new(io::stdout()),
+        };
+        for mut walk in walks {
+            walk.stepping = stepping;
+            walk.wrap = wrap;
+            writeln!(out, "{}: {} trees", walk.slope, count_trees(&field, &walk))?;
+            writeln!(
+                out,
+                "{}",
+                PathOverlay {
+                    field: &field,
+                    walk
+                }
+            )?;
+        }
+        return Ok(());
+    }
+
+    if !walks.is_empty() {
+        for mut walk in walks {
+            walk.stepping = stepping;
+            walk.wrap = wrap;
+            println!("{}: {}", walk.slope, count_trees(&field, &walk));
+        }
+        return Ok(());
+    }
+
     println!("Part 1");
     part_one(&field);
 
@@ -0,0 +1,106 @@
+use ::num::integer::Roots;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::num;
+
+#[derive(Debug)]
+enum Error {
+    ParseInt(num::ParseIntError),
+    Io(io::Error),
+    NoLoopSize(u64),
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<num::ParseIntError> for Error {
+    fn from(e: num::ParseIntError) -> Self {
+        Self::ParseInt(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ParseInt(e) => write!(f, "{}", e),
+            Self::Io(e) => write!(f, "{}", e),
+            Self::NoLoopSize(key) => write!(f, "no loop size produces public key {}", key),
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+const SUBJECT: u64 = 7;
+const MODULUS: u64 = 20201227;
+
+fn mul_mod(a: u64, b: u64, modulus: u64) -> u64 {
+    ((a as u128 * b as u128) % modulus as u128) as u64
+}
+
+fn mod_pow(mut base: u64, mut exp: u64, modulus: u64) -> u64 {
+    let mut result = 1 % modulus;
+    base %= modulus;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_mod(result, base, modulus);
+        }
+        base = mul_mod(base, base, modulus);
+        exp >>= 1;
+    }
+    result
+}
+
+fn discrete_log(base: u64, target: u64, modulus: u64) -> Option<u64> {
+    let target = target % modulus;
+    let m = modulus.sqrt() + 1;
+
+    let mut baby_steps = HashMap::new();
+    let mut value = target;
+    for j in 0..m {
+        baby_steps.insert(value, j);
+        value = mul_mod(value, base, modulus);
+    }
+
+    let giant = mod_pow(base, m, modulus);
+    let mut value = 1 % modulus;
+    for i in 1..=m {
+        value = mul_mod(value, giant, modulus);
+        if let Some(&j) = baby_steps.get(&value) {
+            return Some(i * m - j);
+        }
+    }
+    None
+}
+
+fn find_loop_size(public_key: u64) -> Result<u64> {
+    discrete_log(SUBJECT, public_key, MODULUS).ok_or(Error::NoLoopSize(public_key))
+}
+
+fn part_one(card_key: u64, door_key: u64) -> Result<()> {
+    let card_loop_size = find_loop_size(card_key)?;
+    let door_loop_size = find_loop_size(door_key)?;
+
+    let encryption_key = mod_pow(door_key, card_loop_size, MODULUS);
+    assert_eq!(encryption_key, mod_pow(card_key, door_loop_size, MODULUS));
+
+    println!("{}", encryption_key);
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    let card_key: u64 = lines.next().unwrap()?.parse()?;
+    let door_key: u64 = lines.next().unwrap()?.parse()?;
+
+    println!("Part 1");
+    part_one(card_key, door_key)?;
+
+    Ok(())
+}
@@ -0,0 +1,261 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::io::{self, BufRead};
+use std::ops;
+use std::str::FromStr;
+
+#[derive(Debug)]
+enum Error {
+    Io(io::Error),
+    Parse(String),
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "{}", e),
+            Self::Parse(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+fn parse_lines<R: BufRead, T: FromStr>(lines: R) -> Result<Vec<T>>
+where
+    Error: From<<T as FromStr>::Err>,
+{
+    let mut values = vec![];
+    for maybe_line in lines.lines() {
+        values.push(maybe_line?.parse()?);
+    }
+    Ok(values)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Position {
+    x: isize,
+    y: isize,
+}
+
+impl Position {
+    fn new(x: isize, y: isize) -> Self {
+        Self { x, y }
+    }
+
+    fn adjacent(self) -> impl Iterator<Item = Position> {
+        Direction::ALL.iter().map(move |d| self + d.to_vector())
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Vector {
+    x: isize,
+    y: isize,
+}
+
+impl Vector {
+    const fn new(x: isize, y: isize) -> Self {
+        Self { x, y }
+    }
+}
+
+impl ops::Add<Vector> for Position {
+    type Output = Position;
+
+    fn add(mut self, other: Vector) -> Self {
+        self += other;
+        self
+    }
+}
+
+impl ops::AddAssign<Vector> for Position {
+    fn add_assign(&mut self, other: Vector) {
+        self.x += other.x;
+        self.y += other.y;
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Direction {
+    East,
+    SouthEast,
+    SouthWest,
+    West,
+    NorthWest,
+    NorthEast,
+}
+
+impl Direction {
+    const ALL: [Self; 6] = [
+        Self::East,
+        Self::SouthEast,
+        Self::SouthWest,
+        Self::West,
+        Self::NorthWest,
+        Self::NorthEast,
+    ];
+
+    fn to_vector(self) -> Vector {
+        match self {
+            Self::East => Vector::new(1, 0),
+            Self::SouthEast => Vector::new(0, 1),
+            Self::SouthWest => Vector::new(-1, 1),
+            Self::West => Vector::new(-1, 0),
+            Self::NorthWest => Vector::new(0, -1),
+            Self::NorthEast => Vector::new(1, -1),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Path(Vec<Direction>);
+
+impl Path {
+    fn destination(&self) -> Position {
+        let mut position = Position::new(0, 0);
+        for d in &self.0 {
+            position += d.to_vector();
+        }
+        position
+    }
+}
+
+impl FromStr for Path {
+    type Err = Error;
+    fn from_str(input: &str) -> Result<Self> {
+        let mut directions = vec![];
+        let mut chars = input.chars();
+        while let Some(c) = chars.next() {
+            let d = match c {
+                'e' => Direction::East,
+                'w' => Direction::West,
+                'n' | 's' => match (c, chars.next()) {
+                    ('s', Some('e')) => Direction::SouthEast,
+                    ('s', Some('w')) => Direction::SouthWest,
+                    ('n', Some('w')) => Direction::NorthWest,
+                    ('n', Some('e')) => Direction::NorthEast,
+                    (c, n) => {
+                        return Err(Error::Parse(format!(
+                            "unknown direction {}{}",
+                            c,
+                            n.map(String::from).unwrap_or_default()
+                        )))
+                    }
+                },
+                c => return Err(Error::Parse(format!("unknown direction {}", c))),
+            };
+            directions.push(d);
+        }
+        Ok(Self(directions))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Tile {
+    Black,
+    White,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Floor {
+    black: HashSet<Position>,
+}
+
+impl Floor {
+    fn with_paths(paths: &[Path]) -> Self {
+        let mut floor = Self::default();
+        for p in paths {
+            floor.flip(p.destination());
+        }
+        floor
+    }
+
+    fn get_tile(&self, position: Position) -> Tile {
+        if self.black.contains(&position) {
+            Tile::Black
+        } else {
+            Tile::White
+        }
+    }
+
+    fn set_tile(&mut self, position: Position, tile: Tile) {
+        match tile {
+            Tile::Black => self.black.insert(position),
+            Tile::White => self.black.remove(&position),
+        };
+    }
+
+    fn flip(&mut self, position: Position) {
+        match self.get_tile(position) {
+            Tile::Black => self.set_tile(position, Tile::White),
+            Tile::White => self.set_tile(position, Tile::Black),
+        }
+    }
+
+    fn black_neighbor_counts(&self) -> HashMap<Position, usize> {
+        let mut counts = HashMap::new();
+        for &position in &self.black {
+            counts.entry(position).or_insert(0);
+            for n in position.adjacent() {
+                *counts.entry(n).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    fn apply_changes(&mut self, changes: Vec<(Position, Tile)>) {
+        for (position, t) in changes.into_iter() {
+            self.set_tile(position, t);
+        }
+    }
+
+    fn tick(&mut self) {
+        let mut changes = vec![];
+        for (position, black_neighbors) in self.black_neighbor_counts() {
+            match self.get_tile(position) {
+                Tile::Black if black_neighbors == 0 || black_neighbors > 2 => {
+                    changes.push((position, Tile::White))
+                }
+                Tile::White if black_neighbors == 2 => changes.push((position, Tile::Black)),
+                _ => {}
+            }
+        }
+        self.apply_changes(changes);
+    }
+
+    fn count_active(&self) -> usize {
+        self.black.len()
+    }
+}
+
+fn part_one(paths: &[Path]) {
+    let floor = Floor::with_paths(paths);
+    println!("{}", floor.count_active());
+}
+
+fn part_two(paths: &[Path]) {
+    let mut floor = Floor::with_paths(paths);
+    for _ in 0..100 {
+        floor.tick();
+    }
+    println!("{}", floor.count_active());
+}
+
+fn main() -> Result<()> {
+    let paths: Vec<Path> = parse_lines(io::stdin().lock())?;
+
+    println!("Part 1");
+    part_one(&paths);
+
+    println!("Part 2");
+    part_two(&paths);
+
+    Ok(())
+}
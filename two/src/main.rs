@@ -1,9 +1,11 @@
 use combine::parser::char::{char, digit, letter, spaces};
 use combine::stream::Stream;
-use combine::{many1, EasyParser, Parser};
+use combine::{many1, satisfy, EasyParser, Parser};
+use std::env;
 use std::io::{self, BufRead};
-use std::num;
+use std::process;
 use std::str::FromStr;
+use std::{fmt, num};
 
 #[derive(Debug)]
 enum Error {
@@ -11,6 +13,19 @@ enum Error {
     Io(io::Error),
     ParseError(String),
     ExtraneousInput,
+    UnknownPolicy(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ParseInt(e) => write!(f, "{}", e),
+            Self::Io(e) => write!(f, "{}", e),
+            Self::ParseError(e) => write!(f, "{}", e.trim_end()),
+            Self::ExtraneousInput => write!(f, "unexpected trailing input"),
+            Self::UnknownPolicy(e) => write!(f, "unknown policy {}", e),
+        }
+    }
 }
 
 impl From<io::Error> for Error {
@@ -47,12 +62,24 @@ where
 #[derive(Debug)]
 struct Numbers(usize, usize);
 
+impl fmt::Display for Numbers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.0, self.1)
+    }
+}
+
 #[derive(Debug)]
 struct PasswordPolicy {
     numbers: Numbers,
     letter: char,
 }
 
+impl fmt::Display for PasswordPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.numbers, self.letter)
+    }
+}
+
 fn require_no_remaining(remaining: &str) -> Result<()> {
     if remaining != "" {
         Err(Error::ExtraneousInput)
@@ -61,6 +88,24 @@ fn require_no_remaining(remaining: &str) -> Result<()> {
     }
 }
 
+#[derive(Debug)]
+struct PositionOutOfRange {
+    position: usize,
+    length: usize,
+}
+
+impl fmt::Display for PositionOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "position {} is out of range for password of length {}",
+            self.position, self.length
+        )
+    }
+}
+
+type PositionResult<T> = std::result::Result<T, PositionOutOfRange>;
+
 impl PasswordPolicy {
     fn parser<Input>() -> impl Parser<Input, Output = Self>
     where
@@ -77,14 +122,26 @@ impl PasswordPolicy {
             .map(|(numbers, letter)| Self { numbers, letter })
     }
 
-    fn part_one_validate(&self, password: &str) -> bool {
-        let n = password.chars().filter(|c| *c == self.letter).count();
-        n >= self.numbers.0 && n <= self.numbers.1
+    fn letter_count(&self, password: &str) -> usize {
+        password.chars().filter(|c| *c == self.letter).count()
     }
 
-    fn part_two_validate(&self, password: &str) -> bool {
-        (password.chars().nth(self.numbers.0 - 1).unwrap() == self.letter)
-            ^ (password.chars().nth(self.numbers.1 - 1).unwrap() == self.letter)
+    fn letter_at(&self, password: &str, position: usize) -> PositionResult<bool> {
+        let c = position
+            .checked_sub(1)
+            .and_then(|i| password.chars().nth(i))
+            .ok_or_else(|| PositionOutOfRange {
+                position,
+                length: password.chars().count(),
+            })?;
+        Ok(c == self.letter)
+    }
+
+    fn letter_at_positions(&self, password: &str) -> PositionResult<(bool, bool)> {
+        Ok((
+            self.letter_at(password, self.numbers.0)?,
+            self.letter_at(password, self.numbers.1)?,
+        ))
     }
 }
 
@@ -103,23 +160,22 @@ struct PasswordDatabaseEntry {
     password: String,
 }
 
+impl fmt::Display for PasswordDatabaseEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.policy, self.password)
+    }
+}
+
 impl PasswordDatabaseEntry {
     fn parser<Input>() -> impl Parser<Input, Output = Self>
     where
         Input: Stream<Token = char>,
     {
         let policy = PasswordPolicy::parser();
-        let parser = policy.skip(char(':')).and(spaces().with(many1(letter())));
+        let password = many1(satisfy(|c: char| !c.is_whitespace()));
+        let parser = policy.skip(char(':')).and(spaces().with(password));
         parser.map(|(policy, password)| Self { policy, password })
     }
-
-    fn part_one_valid(&self) -> bool {
-        self.policy.part_one_validate(&self.password)
-    }
-
-    fn part_two_valid(&self) -> bool {
-        self.policy.part_two_validate(&self.password)
-    }
 }
 
 impl FromStr for PasswordDatabaseEntry {
@@ -131,23 +187,344 @@ impl FromStr for PasswordDatabaseEntry {
     }
 }
 
+type Check = std::result::Result<(), String>;
+
+trait Policy {
+    fn name(&self) -> String;
+    fn check(&self, entry: &PasswordDatabaseEntry) -> Check;
+
+    fn validate(&self, entry: &PasswordDatabaseEntry) -> bool {
+        self.check(entry).is_ok()
+    }
+}
+
+fn describe_positions(policy: &PasswordPolicy, found: (bool, bool)) -> String {
+    let Numbers(first, second) = policy.numbers;
+    match found {
+        (true, true) => format!(
+            "letter '{}' at both positions {} and {}",
+            policy.letter, first, second
+        ),
+        (true, false) => format!(
+            "letter '{}' at position {} but not {}",
+            policy.letter, first, second
+        ),
+        (false, true) => format!(
+            "letter '{}' at position {} but not {}",
+            policy.letter, second, first
+        ),
+        (false, false) => format!(
+            "letter '{}' at neither position {} nor {}",
+            policy.letter, first, second
+        ),
+    }
+}
+
+struct CountRange;
+
+impl CountRange {
+    const NAME: &'static str = "count-range";
+}
+
+impl Policy for CountRange {
+    fn name(&self) -> String {
+        Self::NAME.into()
+    }
+
+    fn check(&self, entry: &PasswordDatabaseEntry) -> Check {
+        let n = entry.policy.letter_count(&entry.password);
+        if n >= entry.policy.numbers.0 && n <= entry.policy.numbers.1 {
+            Ok(())
+        } else {
+            Err(format!(
+                "letter '{}' appeared {} times, allowed {}",
+                entry.policy.letter, n, entry.policy.numbers
+            ))
+        }
+    }
+}
+
+struct Positions {
+    name: &'static str,
+    combine: fn(bool, bool) -> bool,
+}
+
+impl Positions {
+    const EXACTLY_ONE: Self = Self {
+        name: "exactly-one-position",
+        combine: |first, second| first ^ second,
+    };
+    const BOTH: Self = Self {
+        name: "exact-positions",
+        combine: |first, second| first && second,
+    };
+    const EITHER: Self = Self {
+        name: "any-position",
+        combine: |first, second| first || second,
+    };
+}
+
+impl Policy for Positions {
+    fn name(&self) -> String {
+        self.name.into()
+    }
+
+    fn check(&self, entry: &PasswordDatabaseEntry) -> Check {
+        let found = entry
+            .policy
+            .letter_at_positions(&entry.password)
+            .map_err(|e| e.to_string())?;
+        let (first, second) = found;
+        if (self.combine)(first, second) {
+            Ok(())
+        } else {
+            Err(describe_positions(&entry.policy, found))
+        }
+    }
+}
+
+struct MinimumLength(usize);
+
+impl MinimumLength {
+    const NAME: &'static str = "min-length";
+}
+
+impl Policy for MinimumLength {
+    fn name(&self) -> String {
+        format!("{}={}", Self::NAME, self.0)
+    }
+
+    fn check(&self, entry: &PasswordDatabaseEntry) -> Check {
+        let length = entry.password.chars().count();
+        if length >= self.0 {
+            Ok(())
+        } else {
+            Err(format!(
+                "password has {} characters, minimum {}",
+                length, self.0
+            ))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum CharacterClass {
+    Lowercase,
+    Uppercase,
+    Digit,
+    Other,
+}
+
+impl CharacterClass {
+    fn of(c: char) -> Self {
+        if c.is_lowercase() {
+            Self::Lowercase
+        } else if c.is_uppercase() {
+            Self::Uppercase
+        } else if c.is_numeric() {
+            Self::Digit
+        } else {
+            Self::Other
+        }
+    }
+}
+
+struct CharacterClasses(usize);
+
+impl CharacterClasses {
+    const NAME: &'static str = "char-classes";
+}
+
+impl Policy for CharacterClasses {
+    fn name(&self) -> String {
+        format!("{}={}", Self::NAME, self.0)
+    }
+
+    fn check(&self, entry: &PasswordDatabaseEntry) -> Check {
+        let mut classes: Vec<_> = entry.password.chars().map(CharacterClass::of).collect();
+        classes.sort();
+        classes.dedup();
+        if classes.len() >= self.0 {
+            Ok(())
+        } else {
+            Err(format!(
+                "password uses {} character classes, minimum {}",
+                classes.len(),
+                self.0
+            ))
+        }
+    }
+}
+
+enum PolicyBuilder {
+    Fixed(fn() -> Box<dyn Policy>),
+    WithCount(fn(usize) -> Box<dyn Policy>),
+}
+
+const POLICIES: &[(&str, PolicyBuilder)] = &[
+    (
+        CountRange::NAME,
+        PolicyBuilder::Fixed(|| Box::new(CountRange)),
+    ),
+    (
+        Positions::EXACTLY_ONE.name,
+        PolicyBuilder::Fixed(|| Box::new(Positions::EXACTLY_ONE)),
+    ),
+    (
+        Positions::BOTH.name,
+        PolicyBuilder::Fixed(|| Box::new(Positions::BOTH)),
+    ),
+    (
+        Positions::EITHER.name,
+        PolicyBuilder::Fixed(|| Box::new(Positions::EITHER)),
+    ),
+    (
+        MinimumLength::NAME,
+        PolicyBuilder::WithCount(|n| Box::new(MinimumLength(n))),
+    ),
+    (
+        CharacterClasses::NAME,
+        PolicyBuilder::WithCount(|n| Box::new(CharacterClasses(n))),
+    ),
+];
+
+fn policy_usage() -> String {
+    let names: Vec<_> = POLICIES
+        .iter()
+        .map(|(name, builder)| match builder {
+            PolicyBuilder::Fixed(_) => name.to_string(),
+            PolicyBuilder::WithCount(_) => format!("{}=N", name),
+        })
+        .collect();
+    names.join(", ")
+}
+
+fn policy_from_spec(spec: &str) -> Result<Box<dyn Policy>> {
+    let mut parts = spec.splitn(2, '=');
+    let name = parts.next().unwrap();
+    let argument = parts.next();
+    let unknown = || Error::UnknownPolicy(format!("{} (known policies: {})", spec, policy_usage()));
+
+    let (_, builder) = POLICIES
+        .iter()
+        .find(|(n, _)| *n == name)
+        .ok_or_else(unknown)?;
+    Ok(match (builder, argument) {
+        (PolicyBuilder::Fixed(build), None) => build(),
+        (PolicyBuilder::WithCount(build), Some(n)) => build(n.parse()?),
+        _ => return Err(unknown()),
+    })
+}
+
+fn count_valid(entries: &[PasswordDatabaseEntry], policy: &dyn Policy) -> usize {
+    entries.iter().filter(|e| policy.validate(e)).count()
+}
+
+struct Rejection<'a> {
+    line: usize,
+    entry: &'a PasswordDatabaseEntry,
+    policy: String,
+    reason: String,
+}
+
+fn audit<'a>(
+    entries: &'a [PasswordDatabaseEntry],
+    policies: &[Box<dyn Policy>],
+) -> Vec<Rejection<'a>> {
+    let mut rejections = vec![];
+    for (i, entry) in entries.iter().enumerate() {
+        for policy in policies {
+            if let Err(reason) = policy.check(entry) {
+                rejections.push(Rejection {
+                    line: i + 1,
+                    entry,
+                    policy: policy.name(),
+                    reason,
+                });
+            }
+        }
+    }
+    rejections
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.into()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AuditFormat {
+    Text,
+    Csv,
+}
+
+fn print_audit(rejections: &[Rejection<'_>], format: AuditFormat) {
+    match format {
+        AuditFormat::Text => {
+            for r in rejections {
+                println!(
+                    "line {}: \"{}\" rejected by {}: {}",
+                    r.line, r.entry, r.policy, r.reason
+                );
+            }
+        }
+        AuditFormat::Csv => {
+            println!("line,entry,policy,reason");
+            for r in rejections {
+                println!(
+                    "{},{},{},{}",
+                    r.line,
+                    csv_field(&r.entry.to_string()),
+                    csv_field(&r.policy),
+                    csv_field(&r.reason)
+                );
+            }
+        }
+    }
+}
+
 fn part_one(entries: &[PasswordDatabaseEntry]) {
-    println!(
-        "{:?}",
-        entries.iter().filter(|e| e.part_one_valid()).count()
-    );
+    println!("{:?}", count_valid(entries, &CountRange));
 }
 
 fn part_two(entries: &[PasswordDatabaseEntry]) {
-    println!(
-        "{:?}",
-        entries.iter().filter(|e| e.part_two_valid()).count()
-    );
+    println!("{:?}", count_valid(entries, &Positions::EXACTLY_ONE));
 }
 
-fn main() -> Result<()> {
+fn run() -> Result<()> {
+    let mut audit_format = None;
+    let mut policies = vec![];
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--audit" => audit_format = Some(AuditFormat::Text),
+            "--csv" => audit_format = Some(AuditFormat::Csv),
+            spec => policies.push(policy_from_spec(spec)?),
+        }
+    }
     let entries: Vec<PasswordDatabaseEntry> = parse_lines(io::stdin().lock())?;
 
+    if let Some(format) = audit_format {
+        if policies.is_empty() {
+            policies = vec![Box::new(CountRange), Box::new(Positions::EXACTLY_ONE)];
+        }
+        print_audit(&audit(&entries, &policies), format);
+        return Ok(());
+    }
+
+    if !policies.is_empty() {
+        for policy in &policies {
+            println!(
+                "{}: {}",
+                policy.name(),
+                count_valid(&entries, policy.as_ref())
+            );
+        }
+        return Ok(());
+    }
+
     println!("Part 1");
     part_one(&entries);
 
@@ -156,3 +533,10 @@ fn main() -> Result<()> {
 
     Ok(())
 }
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}